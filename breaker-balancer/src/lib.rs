@@ -0,0 +1,393 @@
+//! Load balancing with a circuit breaker per backend
+
+use circuit_breaker_simulator::{CircuitBreaker, CircuitState};
+use load_balancer_simulator::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+type BreakerFactory<B> = Box<dyn FnMut(usize) -> B + Send>;
+
+/// Wraps a strategy so that every server has its own breaker and a server whose
+/// breaker is open is taken out of rotation.
+///
+/// Each `Selected` server has had a call admitted by its breaker, and the caller
+/// must report how that call went with exactly one `record_result`. A failure
+/// that opens the breaker marks the server unhealthy. Every later selection
+/// offers each breaker that does not permit calls a call it must reject, which
+/// moves a count-based breaker towards half-open, and marks a tripped server
+/// healthy again once its breaker permits calls.
+///
+/// Servers whose breaker does not permit calls are excluded before the inner
+/// strategy selects, and only the selected server's breaker is asked to admit
+/// the call. When it still rejects, the selection's connection is released on
+/// the inner strategy before selecting again, so no connection is counted for a
+/// call that never happens.
+pub struct BreakerBalancer<L, B> {
+    inner: L,
+    breakers: HashMap<usize, B>,
+    new_breaker: BreakerFactory<B>,
+    // Servers this wrapper marked unhealthy because their breaker opened.
+    tripped: HashSet<usize>,
+}
+
+impl<L, B: CircuitBreaker> BreakerBalancer<L, B> {
+    /// Gives every server of `inner`, and every server added later, the breaker
+    /// `new_breaker(server_id)`.
+    pub fn new<T>(inner: L, mut new_breaker: impl FnMut(usize) -> B + Send + 'static) -> Self
+    where
+        L: LoadBalancer<T>,
+    {
+        let breakers = inner
+            .servers()
+            .iter()
+            .map(|server| (server.id, new_breaker(server.id)))
+            .collect();
+
+        Self {
+            inner,
+            breakers,
+            new_breaker: Box::new(new_breaker),
+            tripped: HashSet::new(),
+        }
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    /// The breaker guarding `server_id`. Panics on an unknown id.
+    pub fn breaker(&self, server_id: usize) -> &B {
+        match self.breakers.get(&server_id) {
+            Some(breaker) => breaker,
+            None => panic!("unknown server id {server_id}"),
+        }
+    }
+
+    /// Reports the outcome of the call made to a server returned by a selection.
+    pub fn record_result<T>(&mut self, server_id: usize, ok: bool)
+    where
+        L: LoadBalancer<T>,
+    {
+        let breaker = self.breaker_mut(server_id);
+        if ok {
+            breaker.record_success();
+        } else {
+            breaker.record_failure();
+        }
+
+        if breaker.state() == CircuitState::Open
+            && self.inner.server_state(server_id) == ServerState::Healthy
+        {
+            self.inner.unhealthy_server(server_id);
+            self.tripped.insert(server_id);
+        }
+    }
+
+    fn breaker_mut(&mut self, server_id: usize) -> &mut B {
+        match self.breakers.get_mut(&server_id) {
+            Some(breaker) => breaker,
+            None => panic!("unknown server id {server_id}"),
+        }
+    }
+
+    /// Whether the breaker of `server_id` refuses calls. A refusing breaker is
+    /// offered a call anyway, which it must reject, so a count-based breaker
+    /// moves towards half-open without any call being admitted.
+    fn refuses_calls(&mut self, server_id: usize) -> bool {
+        let breaker = self.breaker_mut(server_id);
+        if breaker.is_call_permitted() {
+            return false;
+        }
+        let admitted = breaker.try_acquire();
+        assert!(
+            !admitted,
+            "breaker of server {server_id} admitted a refused call"
+        );
+        true
+    }
+
+    /// Puts the tripped servers whose breaker permits calls back in rotation and
+    /// returns the healthy servers whose breaker refuses them.
+    fn closed_to_calls<T>(&mut self, exclude: &[usize]) -> Vec<usize>
+    where
+        L: LoadBalancer<T>,
+    {
+        let mut tripped: Vec<usize> = self.tripped.iter().copied().collect();
+        tripped.sort_unstable();
+        for server_id in tripped {
+            if !self.refuses_calls(server_id) {
+                self.tripped.remove(&server_id);
+                self.inner.healthy_server(server_id);
+            }
+        }
+
+        let candidates: Vec<usize> = self
+            .inner
+            .servers()
+            .iter()
+            .filter(|server| server.state == ServerState::Healthy)
+            .map(|server| server.id)
+            .filter(|id| !exclude.contains(id))
+            .collect();
+        candidates
+            .into_iter()
+            .filter(|&server_id| self.refuses_calls(server_id))
+            .collect()
+    }
+
+    /// Runs a selection from `select` among servers whose breaker permits calls,
+    /// releasing and moving on past any whose breaker still rejects the call.
+    fn select_admitted<T>(
+        &mut self,
+        exclude: &[usize],
+        mut select: impl FnMut(&mut L, &[usize]) -> LoadBalancerResult,
+    ) -> LoadBalancerResult
+    where
+        L: LoadBalancer<T>,
+    {
+        let mut rejected: Vec<usize> = exclude.to_vec();
+        let closed = self.closed_to_calls(exclude);
+        rejected.extend(closed);
+        loop {
+            let result = select(&mut self.inner, &rejected);
+            let LoadBalancerResult::Selected { id } = result else {
+                return result;
+            };
+            assert!(!rejected.contains(&id));
+            if self.breaker_mut(id).try_acquire() {
+                return result;
+            }
+            self.inner.release_connection(id);
+            rejected.push(id);
+        }
+    }
+}
+
+impl<T, L: LoadBalancer<T>, B: CircuitBreaker> LoadBalancer<T> for BreakerBalancer<L, B> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        self.select_admitted(&[], |inner, rejected| {
+            if rejected.is_empty() {
+                inner.select_server()
+            } else {
+                inner.select_excluding(rejected)
+            }
+        })
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        self.select_admitted(exclude, |inner, rejected| inner.select_excluding(rejected))
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        self.tripped.remove(&server_id);
+        self.inner.healthy_server(server_id);
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        self.tripped.remove(&server_id);
+        self.inner.unhealthy_server(server_id);
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        self.tripped.remove(&server_id);
+        self.inner.drain_server(server_id);
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        self.tripped.clear();
+        self.inner.set_states(states);
+    }
+
+    fn record_latency(&mut self, server_id: usize, latency: Duration) {
+        self.inner.record_latency(server_id, latency);
+    }
+
+    fn release_connection(&mut self, server_id: usize) {
+        self.inner.release_connection(server_id);
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        let server_id = server.id;
+        self.inner.add_server(server);
+        let breaker = (self.new_breaker)(server_id);
+        self.breakers.insert(server_id, breaker);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        self.inner.remove_server(server_id);
+        self.breakers.remove(&server_id);
+        self.tripped.remove(&server_id);
+    }
+
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        self.inner.server(server_id)
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        self.inner.servers()
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.inner.healthy_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use circuit_breaker_simulator::{CircuitError, CountCB};
+    use load_balancer_simulator::{LeastConnections, RoundRobin};
+
+    /// Permits every call but admits none when `admits` is false, like a breaker
+    /// whose admission hangs on a draw `is_call_permitted` cannot see.
+    struct Gate {
+        admits: bool,
+    }
+
+    impl CircuitBreaker for Gate {
+        fn call_classified<F, R, E, P>(
+            &mut self,
+            f: F,
+            _should_count_failure: P,
+        ) -> Result<R, CircuitError<E>>
+        where
+            F: FnOnce() -> Result<R, E>,
+            P: FnOnce(&E) -> bool,
+        {
+            if !self.try_acquire() {
+                return Err(CircuitError::Rejected);
+            }
+            f().map_err(CircuitError::Failed)
+        }
+
+        fn try_acquire(&mut self) -> bool {
+            self.admits
+        }
+
+        fn record_success(&mut self) {}
+
+        fn record_failure(&mut self) {}
+
+        fn state(&self) -> CircuitState {
+            CircuitState::Closed
+        }
+
+        fn reset(&mut self) {}
+
+        fn trip(&mut self) {}
+
+        fn is_call_permitted(&self) -> bool {
+            true
+        }
+
+        fn version(&self) -> u64 {
+            0
+        }
+    }
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    fn selected_id(result: LoadBalancerResult) -> usize {
+        match result {
+            LoadBalancerResult::Selected { id } => id,
+            other => panic!("expected a selection, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_open_breaker_takes_server_out_of_rotation() {
+        let mut lb = BreakerBalancer::new(RoundRobin::new(servers(3)), |_| CountCB::new(2, 3));
+        for _ in 0..6 {
+            let id = selected_id(lb.select_server());
+            lb.record_result(id, id != 1);
+        }
+        assert_eq!(lb.breaker(1).state(), CircuitState::Open);
+        assert_eq!(lb.server_state(1), ServerState::Unhealthy);
+        assert_eq!(lb.healthy_count(), 2);
+
+        let selected: Vec<_> = (0..2)
+            .map(|_| {
+                let id = selected_id(lb.select_server());
+                lb.record_result(id, true);
+                id
+            })
+            .collect();
+        assert!(!selected.contains(&1));
+    }
+
+    #[test]
+    fn test_recovered_breaker_returns_server_to_rotation() {
+        let mut lb = BreakerBalancer::new(RoundRobin::new(servers(2)), |_| CountCB::new(1, 2));
+        assert_eq!(selected_id(lb.select_server()), 0);
+        lb.record_result(0, false);
+        assert_eq!(lb.server_state(0), ServerState::Unhealthy);
+
+        for _ in 0..2 {
+            let id = selected_id(lb.select_server());
+            assert_eq!(id, 1);
+            lb.record_result(id, true);
+        }
+        assert_eq!(lb.breaker(0).state(), CircuitState::HalfOpen);
+
+        let probe = selected_id(lb.select_server());
+        assert_eq!(probe, 0);
+        lb.record_result(probe, true);
+        assert_eq!(lb.breaker(0).state(), CircuitState::Closed);
+        assert_eq!(lb.healthy_count(), 2);
+    }
+
+    #[test]
+    fn test_all_breakers_open_reports_no_healthy_servers() {
+        let mut lb = BreakerBalancer::new(RoundRobin::new(servers(2)), |_| CountCB::new(1, 5));
+        for _ in 0..2 {
+            let id = selected_id(lb.select_server());
+            lb.record_result(id, false);
+        }
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+
+        lb.add_server(Server::new(2, ServerState::Healthy));
+        assert_eq!(lb.breaker(2).state(), CircuitState::Closed);
+        assert_eq!(selected_id(lb.select_server()), 2);
+    }
+
+    #[test]
+    fn test_open_breaker_leaves_no_phantom_connection() {
+        let mut lb =
+            BreakerBalancer::new(LeastConnections::new(servers(2)), |_| CountCB::new(1, 5));
+        assert_eq!(selected_id(lb.select_server()), 0);
+        lb.record_result(0, false);
+        assert_eq!(lb.breaker(0).state(), CircuitState::Open);
+        lb.healthy_server(0);
+
+        for _ in 0..4 {
+            let id = selected_id(lb.select_server());
+            assert_eq!(id, 1);
+            lb.record_result(id, true);
+            assert_eq!(lb.inner().connections(0), 0);
+        }
+        assert_eq!(lb.inner().connections(1), 4);
+        assert_eq!(lb.breaker(0).half_open_attempts(), 4);
+    }
+
+    #[test]
+    fn test_rejected_selection_releases_its_connection() {
+        let mut lb = BreakerBalancer::new(LeastConnections::new(servers(2)), |id| Gate {
+            admits: id != 0,
+        });
+        for _ in 0..3 {
+            let id = selected_id(lb.select_server());
+            assert_eq!(id, 1);
+            lb.record_result(id, true);
+            assert_eq!(lb.inner().connections(0), 0);
+        }
+        assert_eq!(lb.inner().connections(1), 3);
+    }
+}
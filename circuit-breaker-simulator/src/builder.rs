@@ -0,0 +1,203 @@
+//! Named-setter builders for the breakers with positional constructors
+
+use crate::count::CountCB;
+#[cfg(feature = "std")]
+use crate::time::{Clock, RealClock, TimeCB};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::time::Duration;
+
+/// A setting a breaker constructor rejects: `try_new` and `build` return it,
+/// `new` panics with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    ZeroOpenTimeout,
+    ZeroFailureThreshold,
+    ZeroHalfOpenThreshold,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::ZeroOpenTimeout => f.write_str("open timeout must be non-zero"),
+            BuildError::ZeroFailureThreshold => f.write_str("failure threshold must be non-zero"),
+            BuildError::ZeroHalfOpenThreshold => {
+                f.write_str("half-open threshold must be non-zero")
+            }
+        }
+    }
+}
+
+impl core::error::Error for BuildError {}
+
+/// Builds a `CountCB`. Defaults match `CountCB::balanced`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountCBBuilder {
+    failure_threshold: u32,
+    half_open_threshold: u32,
+}
+
+impl Default for CountCBBuilder {
+    fn default() -> Self {
+        CountCBBuilder {
+            failure_threshold: 5,
+            half_open_threshold: 5,
+        }
+    }
+}
+
+impl CountCBBuilder {
+    pub fn failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold;
+        self
+    }
+
+    pub fn half_open_threshold(mut self, half_open_threshold: u32) -> Self {
+        self.half_open_threshold = half_open_threshold;
+        self
+    }
+
+    pub fn build(self) -> Result<CountCB, BuildError> {
+        CountCB::try_new(self.failure_threshold, self.half_open_threshold)
+    }
+}
+
+/// Builds a `TimeCB`. Defaults: open for 60s, trip after 5 consecutive failures,
+/// reopen on the first failed probe, real clock.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeCBBuilder<C: Clock = RealClock> {
+    open_timeout: Duration,
+    closed_failures_threshold: u32,
+    half_open_probes_threshold: u32,
+    clock: C,
+}
+
+#[cfg(feature = "std")]
+impl Default for TimeCBBuilder<RealClock> {
+    fn default() -> Self {
+        TimeCBBuilder {
+            open_timeout: Duration::from_secs(60),
+            closed_failures_threshold: 5,
+            half_open_probes_threshold: 1,
+            clock: RealClock,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<C: Clock> TimeCBBuilder<C> {
+    pub fn open_timeout(mut self, open_timeout: Duration) -> Self {
+        self.open_timeout = open_timeout;
+        self
+    }
+
+    pub fn closed_failures_threshold(mut self, closed_failures_threshold: u32) -> Self {
+        self.closed_failures_threshold = closed_failures_threshold;
+        self
+    }
+
+    pub fn half_open_probes_threshold(mut self, half_open_probes_threshold: u32) -> Self {
+        self.half_open_probes_threshold = half_open_probes_threshold;
+        self
+    }
+
+    pub fn clock<D: Clock>(self, clock: D) -> TimeCBBuilder<D> {
+        TimeCBBuilder {
+            open_timeout: self.open_timeout,
+            closed_failures_threshold: self.closed_failures_threshold,
+            half_open_probes_threshold: self.half_open_probes_threshold,
+            clock,
+        }
+    }
+
+    pub fn build(self) -> Result<TimeCB<C>, BuildError> {
+        TimeCB::try_with_clock(
+            self.open_timeout,
+            self.half_open_probes_threshold,
+            self.closed_failures_threshold,
+            self.clock,
+        )
+    }
+}
+
+impl CountCB {
+    pub fn builder() -> CountCBBuilder {
+        CountCBBuilder::default()
+    }
+}
+
+#[cfg(feature = "std")]
+impl TimeCB<RealClock> {
+    pub fn builder() -> TimeCBBuilder {
+        TimeCBBuilder::default()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::cb::{CircuitBreaker, CircuitState};
+
+    #[test]
+    fn test_count_cb_builder_defaults() {
+        let mut cb = CountCB::builder().build().unwrap();
+        assert_eq!(cb.failures_until_trip(), 5);
+        for _ in 0..5 {
+            let _ = cb.call(|| Err::<(), ()>(()));
+        }
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_count_cb_builder_rejects_zero_thresholds() {
+        assert_eq!(
+            CountCB::builder().failure_threshold(0).build().unwrap_err(),
+            BuildError::ZeroFailureThreshold
+        );
+        assert_eq!(
+            CountCB::builder()
+                .half_open_threshold(0)
+                .build()
+                .unwrap_err(),
+            BuildError::ZeroHalfOpenThreshold
+        );
+    }
+
+    #[test]
+    fn test_time_cb_builder_named_setters() {
+        let cb = TimeCB::builder()
+            .open_timeout(Duration::from_millis(5))
+            .closed_failures_threshold(3)
+            .half_open_probes_threshold(2)
+            .build()
+            .unwrap();
+        assert_eq!(cb.failures_until_trip(), 3);
+        assert_eq!(cb.effective_open_timeout(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_time_cb_builder_rejects_zero_settings() {
+        assert_eq!(
+            TimeCB::builder()
+                .open_timeout(Duration::ZERO)
+                .build()
+                .unwrap_err(),
+            BuildError::ZeroOpenTimeout
+        );
+        assert_eq!(
+            TimeCB::builder()
+                .closed_failures_threshold(0)
+                .build()
+                .unwrap_err(),
+            BuildError::ZeroFailureThreshold
+        );
+        assert_eq!(
+            TimeCB::builder()
+                .half_open_probes_threshold(0)
+                .build()
+                .unwrap_err(),
+            BuildError::ZeroHalfOpenThreshold
+        );
+    }
+}
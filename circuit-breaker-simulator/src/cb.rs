@@ -1,5 +1,12 @@
 //! Circuit Breaker core types and trait
 
+use crate::random::RandomSource;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::collections::VecDeque;
+use core::fmt;
+
 /// Circuit breaker states
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CircuitState {
@@ -9,18 +16,375 @@ pub enum CircuitState {
 }
 
 /// Circuit breaker result
+///
+/// `TimedOut` is a call that returned `Ok` but ran past a slow-call threshold, so
+/// the breaker counted it as a failure and returned `CircuitError::TimedOut`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CircuitResult {
     Rejected,
     Failed,
+    TimedOut,
     Succeeded,
 }
 
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitState::Closed => f.write_str("closed"),
+            CircuitState::Open => f.write_str("open"),
+            CircuitState::HalfOpen => f.write_str("half-open"),
+        }
+    }
+}
+
+impl fmt::Display for CircuitResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitResult::Rejected => f.write_str("rejected"),
+            CircuitResult::Failed => f.write_str("failed"),
+            CircuitResult::TimedOut => f.write_str("timed out"),
+            CircuitResult::Succeeded => f.write_str("succeeded"),
+        }
+    }
+}
+
+impl<R, E> From<&Result<R, CircuitError<E>>> for CircuitResult {
+    fn from(result: &Result<R, CircuitError<E>>) -> Self {
+        match result {
+            Ok(_) => CircuitResult::Succeeded,
+            Err(CircuitError::Failed(_)) => CircuitResult::Failed,
+            Err(CircuitError::TimedOut) => CircuitResult::TimedOut,
+            Err(CircuitError::Rejected) => CircuitResult::Rejected,
+        }
+    }
+}
+
+/// Running call counters for a breaker. `failed` counts calls the breaker treated
+/// as failures; `opens` counts every transition to `Open`, including `trip`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    pub succeeded: u64,
+    pub failed: u64,
+    pub rejected: u64,
+    pub opens: u64,
+}
+
+/// One call as seen by a breaker's event log: when it happened, how it ended and
+/// the state the breaker was left in. `at` is a clock reading for breakers that
+/// have a clock and a sequence number for those that don't.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event<T> {
+    pub at: T,
+    pub result: CircuitResult,
+    pub state: CircuitState,
+}
+
+/// The most recent `capacity` events, oldest first.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub(crate) struct EventLog<T> {
+    events: VecDeque<Event<T>>,
+    capacity: usize,
+    recorded: u64,
+}
+
+#[cfg(feature = "alloc")]
+impl<T> EventLog<T> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        assert!(capacity > 0);
+
+        EventLog {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+            recorded: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, event: Event<T>) {
+        assert!(self.events.len() <= self.capacity);
+
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+        self.recorded += 1;
+    }
+
+    /// Events pushed so far, including those already evicted.
+    pub(crate) fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Event<T>> {
+        self.events.iter()
+    }
+}
+
+/// Circuit breaker error, carrying the closure's own error when it failed.
+/// `TimedOut` is an `Ok` that ran past a slow-call threshold; its value is dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitError<E> {
+    Rejected,
+    Failed(E),
+    TimedOut,
+}
+
+/// Leaves the closure's error out of the message; `source` returns it instead.
+impl<E> fmt::Display for CircuitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::Rejected => f.write_str("call rejected by circuit breaker"),
+            CircuitError::Failed(_) => f.write_str("call failed"),
+            CircuitError::TimedOut => f.write_str("call timed out"),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for CircuitError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            CircuitError::Rejected | CircuitError::TimedOut => None,
+            CircuitError::Failed(error) => Some(error),
+        }
+    }
+}
+
 /// Circuit Breaker trait
 pub trait CircuitBreaker {
-    fn call<F, R>(&mut self, f: F) -> CircuitResult
+    fn call<F, R, E>(&mut self, f: F) -> Result<R, CircuitError<E>>
     where
-        F: FnOnce() -> Result<R, ()>;
+        F: FnOnce() -> Result<R, E>,
+    {
+        self.call_classified(f, |_| true)
+    }
+
+    /// Like `call`, but runs `fallback` instead when the call is rejected, so only
+    /// the closure's own errors are left to handle. A call that ran past a
+    /// slow-call threshold still returns its value, though it counts as a failure.
+    fn call_or_else<F, G, R, E>(&mut self, f: F, fallback: G) -> Result<R, E>
+    where
+        F: FnOnce() -> Result<R, E>,
+        G: FnOnce() -> R,
+    {
+        let mut completed = None;
+        match self.call(|| f().map(|value| completed = Some(value))) {
+            Ok(()) | Err(CircuitError::TimedOut) => Ok(completed.unwrap()),
+            Err(CircuitError::Failed(error)) => Err(error),
+            Err(CircuitError::Rejected) => Ok(fallback()),
+        }
+    }
+
+    /// Like `call`, but an `Err(e)` for which `should_count_failure(&e)` is false is
+    /// handed back as `Failed` without counting toward tripping or changing state,
+    /// e.g. a 404 from a healthy upstream.
+    fn call_classified<F, R, E, P>(
+        &mut self,
+        f: F,
+        should_count_failure: P,
+    ) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+        P: FnOnce(&E) -> bool;
+
+    /// Admits or rejects a call the caller will run elsewhere, e.g. on another
+    /// thread, moving the breaker as `call` would. Every admitted call must be
+    /// followed by exactly one `record_success` or `record_failure`.
+    fn try_acquire(&mut self) -> bool;
+
+    /// Records the outcome of a call admitted by `try_acquire`. Outcomes that land
+    /// while the breaker is `Open` are dropped.
+    fn record_success(&mut self);
+
+    /// See `record_success`.
+    fn record_failure(&mut self);
 
     fn state(&self) -> CircuitState;
+
+    /// Forces the breaker back to `Closed` with all counters cleared.
+    fn reset(&mut self);
+
+    /// Forces the breaker to `Open` as if it had tripped naturally. Tripping an
+    /// already open breaker restarts its open period.
+    fn trip(&mut self);
+
+    /// Whether `call` would run its closure right now. Never changes state.
+    fn is_call_permitted(&self) -> bool;
+
+    /// Counter bumped on every state transition, so a monitor can skip breakers
+    /// whose version has not moved since its last poll.
+    fn version(&self) -> u64;
+}
+
+/// Whether a breaker's `on_transition` callback and random sources must be `Send`.
+///
+/// `SendHooks`, the default, keeps the breaker `Send`, e.g. to share it through a
+/// `SyncCB`. `LocalHooks` takes any `'static` callback, such as one capturing an
+/// `Rc`, and leaves the breaker `!Send`.
+pub trait Hooks {
+    type Transition: ?Sized + FnMut(CircuitState, CircuitState);
+    type Random: ?Sized + RandomSource;
+}
+
+#[derive(Debug)]
+pub struct SendHooks;
+
+#[derive(Debug)]
+pub struct LocalHooks;
+
+impl Hooks for SendHooks {
+    type Transition = dyn FnMut(CircuitState, CircuitState) + Send;
+    type Random = dyn RandomSource + Send;
+}
+
+impl Hooks for LocalHooks {
+    type Transition = dyn FnMut(CircuitState, CircuitState);
+    type Random = dyn RandomSource;
+}
+
+/// Boxed `on_transition` callback; a newtype so breakers can keep deriving `Debug`.
+#[cfg(feature = "alloc")]
+pub(crate) struct TransitionHook<K: Hooks>(pub(crate) Box<K::Transition>);
+
+#[cfg(feature = "alloc")]
+impl<K: Hooks> fmt::Debug for TransitionHook<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TransitionHook")
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TransitionHook<SendHooks> {
+    pub(crate) fn new(f: impl FnMut(CircuitState, CircuitState) + Send + 'static) -> Self {
+        let f: Box<dyn FnMut(CircuitState, CircuitState) + Send> = Box::new(f);
+        TransitionHook(f)
+    }
+
+    pub(crate) fn local(self) -> TransitionHook<LocalHooks> {
+        let f: Box<dyn FnMut(CircuitState, CircuitState)> = self.0;
+        TransitionHook(f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TransitionHook<LocalHooks> {
+    pub(crate) fn new(f: impl FnMut(CircuitState, CircuitState) + 'static) -> Self {
+        let f: Box<dyn FnMut(CircuitState, CircuitState)> = Box::new(f);
+        TransitionHook(f)
+    }
+}
+
+/// Opening is logged as a warning, every other transition as info.
+#[cfg(feature = "log")]
+pub(crate) fn log_transition(name: Option<&str>, from: CircuitState, to: CircuitState) {
+    let name = name.unwrap_or("unnamed");
+    match to {
+        CircuitState::Open => log::warn!("circuit breaker {name}: {from} -> {to}"),
+        CircuitState::Closed | CircuitState::HalfOpen => {
+            log::info!("circuit breaker {name}: {from} -> {to}")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "log"))]
+pub(crate) mod test_logger {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static RECORDS: RefCell<Vec<(log::Level, String)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // Records are kept per thread so tests running in parallel don't see each other's logs.
+    struct CapturingLogger;
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            RECORDS.with(|records| {
+                records
+                    .borrow_mut()
+                    .push((record.level(), record.args().to_string()))
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger;
+
+    pub(crate) fn install() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Info);
+    }
+
+    pub(crate) fn take() -> Vec<(log::Level, String)> {
+        RECORDS.with(|records| records.take())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_state_and_result_display() {
+        let states: Vec<_> = [
+            CircuitState::Closed,
+            CircuitState::Open,
+            CircuitState::HalfOpen,
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        assert_eq!(states, vec!["closed", "open", "half-open"]);
+
+        let results: Vec<_> = [
+            CircuitResult::Rejected,
+            CircuitResult::Failed,
+            CircuitResult::TimedOut,
+            CircuitResult::Succeeded,
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        assert_eq!(
+            results,
+            vec!["rejected", "failed", "timed out", "succeeded"]
+        );
+    }
+
+    #[test]
+    fn test_error_source_is_inner_error() {
+        let inner = "x".parse::<u8>().unwrap_err();
+        let error = CircuitError::Failed(inner.clone());
+        assert_eq!(error.to_string(), "call failed");
+        assert_eq!(
+            error.source().map(ToString::to_string),
+            Some(inner.to_string())
+        );
+
+        let rejected: CircuitError<std::num::ParseIntError> = CircuitError::Rejected;
+        assert_eq!(rejected.to_string(), "call rejected by circuit breaker");
+        assert!(rejected.source().is_none());
+
+        let timed_out: CircuitError<std::num::ParseIntError> = CircuitError::TimedOut;
+        assert_eq!(timed_out.to_string(), "call timed out");
+        assert!(timed_out.source().is_none());
+    }
+
+    #[test]
+    fn test_error_propagates_through_box_dyn_error() {
+        fn parse(input: &str) -> Result<u8, Box<dyn Error>> {
+            let mut cb = crate::CountCB::new(1, 1);
+            Ok(cb.call(|| input.parse::<u8>())?)
+        }
+
+        assert_eq!(parse("7").unwrap(), 7);
+        let error = parse("x").unwrap_err();
+        assert_eq!(error.to_string(), "call failed");
+        assert!(error.source().is_some());
+    }
 }
@@ -0,0 +1,92 @@
+//! Circuit breaker configuration
+
+use crate::count::CountCB;
+use crate::time::{Clock, TimeCB};
+use std::time::Duration;
+
+/// Settings shared by the breaker implementations, so one value can build any of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CbConfig {
+    pub failure_threshold: u32,
+    pub half_open_threshold: u32,
+    pub open_timeout: Duration,
+}
+
+impl CbConfig {
+    pub fn count_cb(&self) -> CountCB {
+        CountCB::new(self.failure_threshold, self.half_open_threshold)
+    }
+
+    pub fn time_cb(&self) -> TimeCB {
+        TimeCB::new(
+            self.open_timeout,
+            self.half_open_threshold,
+            self.failure_threshold,
+        )
+    }
+
+    pub fn time_cb_with_clock<C: Clock>(&self, clock: C) -> TimeCB<C> {
+        TimeCB::with_clock(
+            self.open_timeout,
+            self.half_open_threshold,
+            self.failure_threshold,
+            clock,
+        )
+    }
+}
+
+/// Only generates configs the breaker constructors accept: non-zero thresholds and
+/// an open timeout between 1ms and 1 minute.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for CbConfig {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(CbConfig {
+            failure_threshold: u.int_in_range(1..=u32::MAX)?,
+            half_open_threshold: u.int_in_range(1..=u32::MAX)?,
+            open_timeout: Duration::from_millis(u.int_in_range(1..=60_000)?),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "arbitrary"))]
+mod tests {
+    use super::*;
+    use crate::cb::CircuitBreaker;
+    use crate::sim::{StepCount, StepTime};
+    use arbitrary::{Arbitrary, Unstructured};
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    #[test]
+    fn test_arbitrary_configs_build_breakers() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut bytes = vec![0u8; 4096];
+
+        for _ in 0..100 {
+            rng.fill_bytes(&mut bytes);
+            let mut u = Unstructured::new(&bytes);
+            let config = CbConfig::arbitrary(&mut u).unwrap();
+            assert!(config.failure_threshold > 0);
+            assert!(config.half_open_threshold > 0);
+            assert!(config.open_timeout > Duration::ZERO);
+
+            let mut count_cb = config.count_cb();
+            let steps = Vec::<StepCount>::arbitrary(&mut u).unwrap();
+            for step in steps {
+                let _ = count_cb.call(|| match step {
+                    StepCount::Success => Ok::<(), ()>(()),
+                    StepCount::Failure => Err(()),
+                });
+            }
+
+            let mut time_cb = config.time_cb();
+            let steps = Vec::<StepTime>::arbitrary(&mut u).unwrap();
+            for step in steps {
+                let _ = time_cb.call(|| match step {
+                    StepTime::Success | StepTime::Tick => Ok::<(), ()>(()),
+                    StepTime::Failure => Err(()),
+                });
+            }
+        }
+    }
+}
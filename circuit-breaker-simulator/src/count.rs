@@ -1,96 +1,527 @@
 //! Count-based Circuit Breaker implementation
 
-use crate::cb::{CircuitBreaker, CircuitResult, CircuitState};
+use crate::builder::BuildError;
+use crate::cb::{
+    CircuitBreaker, CircuitError, CircuitResult, CircuitState, Hooks, Metrics, SendHooks,
+};
+#[cfg(feature = "alloc")]
+use crate::cb::{Event, EventLog, LocalHooks, TransitionHook};
+#[cfg(feature = "alloc")]
+use alloc::collections::VecDeque;
+#[cfg(feature = "log")]
+use alloc::string::String;
+#[cfg(not(feature = "alloc"))]
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::sync::mpsc::Sender;
 
+/// Opens after `failure_threshold` failures while `Closed`, then rejects
+/// `half_open_threshold` calls before letting a probe through.
+///
+/// By default only consecutive failures count: any success starts the count
+/// again. With `failure_window`, failures are counted among the last calls
+/// instead, so successes in between no longer hide a failing dependency.
 #[derive(Debug)]
-pub struct CountCB {
+pub struct CountCB<K: Hooks = SendHooks> {
     state: CircuitState,
-    closed_failures: u8,
-    closed_failures_threshold: u8,
-    half_open_attempts: u8,
-    half_open_threshold: u8,
+    closed_failures: u32,
+    closed_failures_threshold: u32,
+    #[cfg(feature = "alloc")]
+    failure_window: Option<u32>,
+    // true for a failed call, oldest first; only kept with `failure_window`.
+    #[cfg(feature = "alloc")]
+    outcomes: VecDeque<bool>,
+    half_open_attempts: u32,
+    half_open_threshold: u32,
+    half_open_successes: u32,
+    half_open_success_threshold: u32,
+    tolerate_half_open_failures: bool,
+    #[cfg(feature = "std")]
+    transition_sender: Option<Sender<(CircuitState, CircuitState)>>,
+    #[cfg(feature = "alloc")]
+    on_transition: Option<TransitionHook<K>>,
+    #[cfg(not(feature = "alloc"))]
+    hooks: PhantomData<K>,
+    version: u64,
+    metrics: Metrics,
+    #[cfg(feature = "alloc")]
+    event_log: Option<EventLog<u64>>,
+    disabled: bool,
+    #[cfg(feature = "log")]
+    name: Option<String>,
 }
 
 impl CountCB {
-    pub fn new(failure_threshold: u8, half_open_threshold: u8) -> Self {
-        assert!(failure_threshold > 0);
-        assert!(half_open_threshold > 0);
+    /// Panics on a setting `try_new` would reject.
+    pub fn new(failure_threshold: u32, half_open_threshold: u32) -> Self {
+        Self::try_new(failure_threshold, half_open_threshold)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
 
-        CountCB {
+    pub fn try_new(failure_threshold: u32, half_open_threshold: u32) -> Result<Self, BuildError> {
+        if failure_threshold == 0 {
+            return Err(BuildError::ZeroFailureThreshold);
+        }
+        if half_open_threshold == 0 {
+            return Err(BuildError::ZeroHalfOpenThreshold);
+        }
+
+        Ok(CountCB {
             state: CircuitState::Closed,
             closed_failures: 0,
             closed_failures_threshold: failure_threshold,
+            #[cfg(feature = "alloc")]
+            failure_window: None,
+            #[cfg(feature = "alloc")]
+            outcomes: VecDeque::new(),
             half_open_attempts: 0,
             half_open_threshold,
+            half_open_successes: 0,
+            half_open_success_threshold: 1,
+            tolerate_half_open_failures: false,
+            #[cfg(feature = "std")]
+            transition_sender: None,
+            #[cfg(feature = "alloc")]
+            on_transition: None,
+            #[cfg(not(feature = "alloc"))]
+            hooks: PhantomData,
+            version: 0,
+            metrics: Metrics::default(),
+            #[cfg(feature = "alloc")]
+            event_log: None,
+            disabled: false,
+            #[cfg(feature = "log")]
+            name: None,
+        })
+    }
+
+    /// Trips after 3 consecutive failures and rejects 10 calls before probing.
+    /// For dependencies where failing fast matters more than availability.
+    pub fn sensitive() -> Self {
+        Self::new(3, 10)
+    }
+
+    /// Trips after 5 consecutive failures and rejects 5 calls before probing.
+    pub fn balanced() -> Self {
+        Self::new(5, 5)
+    }
+
+    /// Trips after 10 consecutive failures and rejects 3 calls before probing.
+    /// For flaky dependencies where availability matters more than failing fast.
+    pub fn tolerant() -> Self {
+        Self::new(10, 3)
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Lets `on_transition` take a callback that is not `Send`, which makes the
+    /// breaker `!Send` too.
+    pub fn local(self) -> CountCB<LocalHooks> {
+        CountCB {
+            state: self.state,
+            closed_failures: self.closed_failures,
+            closed_failures_threshold: self.closed_failures_threshold,
+            failure_window: self.failure_window,
+            outcomes: self.outcomes,
+            half_open_attempts: self.half_open_attempts,
+            half_open_threshold: self.half_open_threshold,
+            half_open_successes: self.half_open_successes,
+            half_open_success_threshold: self.half_open_success_threshold,
+            tolerate_half_open_failures: self.tolerate_half_open_failures,
+            #[cfg(feature = "std")]
+            transition_sender: self.transition_sender,
+            on_transition: self.on_transition.map(TransitionHook::local),
+            version: self.version,
+            metrics: self.metrics,
+            event_log: self.event_log,
+            disabled: self.disabled,
+            #[cfg(feature = "log")]
+            name: self.name,
         }
     }
+
+    #[cfg(feature = "alloc")]
+    /// Calls `f(from, to)` once for every state transition. `f` need not be
+    /// `Send`, so the breaker becomes `!Send`; `on_transition_send` keeps it `Send`.
+    pub fn on_transition(
+        self,
+        f: impl FnMut(CircuitState, CircuitState) + 'static,
+    ) -> CountCB<LocalHooks> {
+        self.local().on_transition(f)
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Like `on_transition`, for a `Send` callback, so the breaker stays `Send`.
+    pub fn on_transition_send(
+        mut self,
+        f: impl FnMut(CircuitState, CircuitState) + Send + 'static,
+    ) -> Self {
+        self.on_transition = Some(TransitionHook::<SendHooks>::new(f));
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl CountCB<LocalHooks> {
+    /// Calls `f(from, to)` once for every state transition.
+    pub fn on_transition(mut self, f: impl FnMut(CircuitState, CircuitState) + 'static) -> Self {
+        self.on_transition = Some(TransitionHook::<LocalHooks>::new(f));
+        self
+    }
 }
 
-impl CircuitBreaker for CountCB {
-    fn call<F, R>(&mut self, f: F) -> CircuitResult
+impl<K: Hooks> CountCB<K> {
+    /// Bypasses the breaker: every call runs and nothing is recorded, not even
+    /// metrics, until `enable`. `state` keeps reporting the state it was left in.
+    pub fn disable(&mut self) {
+        self.disabled = true;
+    }
+
+    /// Turns a disabled breaker back on, starting from `Closed` with cleared counters.
+    pub fn enable(&mut self) {
+        if self.disabled {
+            self.disabled = false;
+            self.reset();
+        }
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        self.metrics
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Events kept by `event_log`, oldest first; empty when it is not enabled.
+    pub fn events(&self) -> impl Iterator<Item = &Event<u64>> {
+        self.event_log.iter().flat_map(EventLog::iter)
+    }
+
+    /// Failures counted while `Closed`, consecutive ones or those within
+    /// `failure_window`; equals the threshold once open.
+    pub fn failure_count(&self) -> u32 {
+        self.closed_failures
+    }
+
+    /// Rejected calls counted toward the next probe while `Open`, or failed probes
+    /// while `HalfOpen` with `tolerate_half_open_failures`.
+    pub fn half_open_attempts(&self) -> u32 {
+        self.half_open_attempts
+    }
+
+    /// Failures left before the breaker opens; 0 once it is no longer `Closed`.
+    pub fn failures_until_trip(&self) -> u32 {
+        match self.state {
+            CircuitState::Closed => {
+                assert!(self.closed_failures < self.closed_failures_threshold);
+                self.closed_failures_threshold - self.closed_failures
+            }
+            CircuitState::Open | CircuitState::HalfOpen => 0,
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Counts failures among the last `window_size` calls while `Closed`, whether
+    /// or not successes were interleaved, instead of only consecutive failures.
+    pub fn failure_window(mut self, window_size: u32) -> Self {
+        assert!(window_size >= self.closed_failures_threshold);
+
+        self.failure_window = Some(window_size);
+        self.outcomes = VecDeque::with_capacity(window_size as usize);
+        self
+    }
+
+    /// Consecutive successful probes needed to close from `HalfOpen`; 1 by default.
+    pub fn half_open_success_threshold(mut self, half_open_success_threshold: u32) -> Self {
+        assert!(half_open_success_threshold > 0);
+
+        self.half_open_success_threshold = half_open_success_threshold;
+        self
+    }
+
+    /// Keeps probing after a half-open failure instead of reopening at once, until
+    /// `half_open_threshold` probes have failed, matching `TimeCB`.
+    pub fn tolerate_half_open_failures(mut self) -> Self {
+        self.tolerate_half_open_failures = true;
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Keeps the last `capacity` calls for `events`, each stamped with its sequence
+    /// number among all calls the breaker has recorded.
+    pub fn event_log(mut self, capacity: usize) -> Self {
+        self.event_log = Some(EventLog::new(capacity));
+        self
+    }
+
+    /// Sends every state transition as `(from, to)` on `tx`.
+    #[cfg(feature = "std")]
+    pub fn transition_sender(mut self, tx: Sender<(CircuitState, CircuitState)>) -> Self {
+        self.transition_sender = Some(tx);
+        self
+    }
+
+    /// Names the breaker in log records.
+    #[cfg(feature = "log")]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    fn transition(&mut self, to: CircuitState) {
+        let from = self.state;
+        assert!(from != to);
+
+        self.state = to;
+        self.version += 1;
+        if to == CircuitState::Open {
+            self.metrics.opens += 1;
+        }
+        #[cfg(feature = "std")]
+        if let Some(tx) = &self.transition_sender {
+            // A dropped receiver means nobody is listening, not that the breaker failed.
+            let _ = tx.send((from, to));
+        }
+        #[cfg(feature = "alloc")]
+        if let Some(TransitionHook(on_transition)) = &mut self.on_transition {
+            on_transition(from, to);
+        }
+        #[cfg(feature = "log")]
+        crate::cb::log_transition(self.name.as_deref(), from, to);
+    }
+
+    /// Counts a closed call toward `closed_failures`.
+    fn push_outcome(&mut self, failed: bool) {
+        #[cfg(feature = "alloc")]
+        if let Some(window_size) = self.failure_window {
+            if self.outcomes.len() == window_size as usize && self.outcomes.pop_front().unwrap() {
+                self.closed_failures -= 1;
+            }
+            self.outcomes.push_back(failed);
+            if failed {
+                self.closed_failures += 1;
+            }
+            assert!(self.outcomes.len() <= window_size as usize);
+            return;
+        }
+        self.closed_failures = if failed { self.closed_failures + 1 } else { 0 };
+    }
+
+    fn clear_outcomes(&mut self) {
+        #[cfg(feature = "alloc")]
+        self.outcomes.clear();
+    }
+
+    fn close(&mut self) {
+        self.closed_failures = 0;
+        self.clear_outcomes();
+        self.half_open_attempts = 0;
+        self.half_open_successes = 0;
+    }
+
+    #[cfg_attr(not(feature = "alloc"), allow(unused_variables))]
+    fn record_event(&mut self, result: CircuitResult) {
+        #[cfg(feature = "alloc")]
+        if let Some(event_log) = &mut self.event_log {
+            event_log.push(Event {
+                at: event_log.recorded(),
+                result,
+                state: self.state,
+            });
+        }
+    }
+}
+
+/// Probability that a fresh `CountCB` with `failure_threshold` trips within `calls`
+/// calls, i.e. that `failure_threshold` consecutive failures occur.
+///
+/// Assumes every call fails independently with probability `p_fail` and stops
+/// counting at the first trip, so recovery and later trips are not modelled.
+#[cfg(feature = "alloc")]
+pub fn trip_probability(failure_threshold: u32, p_fail: f64, calls: u32) -> f64 {
+    assert!(failure_threshold > 0);
+    assert!((0.0..=1.0).contains(&p_fail));
+
+    // runs[k] is the probability of currently sitting on k consecutive failures.
+    let mut runs = alloc::vec![0.0; failure_threshold as usize];
+    runs[0] = 1.0;
+    let mut tripped = 0.0;
+
+    for _ in 0..calls {
+        let last = runs[runs.len() - 1];
+        tripped += p_fail * last;
+
+        let not_tripped: f64 = runs.iter().sum();
+        for k in (1..runs.len()).rev() {
+            runs[k] = p_fail * runs[k - 1];
+        }
+        runs[0] = (1.0 - p_fail) * not_tripped;
+    }
+
+    assert!((-1e-9..=1.0 + 1e-9).contains(&tripped));
+    tripped
+}
+
+impl<K: Hooks> CircuitBreaker for CountCB<K> {
+    fn call_classified<F, R, E, P>(
+        &mut self,
+        f: F,
+        should_count_failure: P,
+    ) -> Result<R, CircuitError<E>>
     where
-        F: FnOnce() -> Result<R, ()>,
+        F: FnOnce() -> Result<R, E>,
+        P: FnOnce(&E) -> bool,
     {
+        if self.disabled {
+            return f().map_err(CircuitError::Failed);
+        }
+        if !self.try_acquire() {
+            return Err(CircuitError::Rejected);
+        }
+
+        match f() {
+            Ok(value) => {
+                self.record_success();
+                Ok(value)
+            }
+            Err(error) => {
+                if should_count_failure(&error) {
+                    self.record_failure();
+                }
+                Err(CircuitError::Failed(error))
+            }
+        }
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        if self.disabled {
+            return true;
+        }
+
         match self.state {
             CircuitState::Closed => {
                 assert!(self.closed_failures < self.closed_failures_threshold);
                 assert!(self.half_open_attempts == 0);
-
-                let result = f();
-                match result {
-                    Ok(_) => {
-                        self.closed_failures = 0;
-                        CircuitResult::Succeeded
-                    }
-                    Err(_) => {
-                        self.closed_failures += 1;
-                        if self.closed_failures == self.closed_failures_threshold {
-                            self.state = CircuitState::Open;
-                        }
-                        CircuitResult::Failed
-                    }
-                }
+                assert!(self.half_open_successes == 0);
+                true
             }
             CircuitState::Open => {
                 assert!(self.closed_failures == self.closed_failures_threshold);
                 assert!(self.half_open_attempts < self.half_open_threshold);
+                assert!(self.half_open_successes == 0);
 
+                self.metrics.rejected += 1;
                 self.half_open_attempts += 1;
                 if self.half_open_attempts == self.half_open_threshold {
-                    self.state = CircuitState::HalfOpen;
+                    self.transition(CircuitState::HalfOpen);
                     self.half_open_attempts = 0;
                 }
-                CircuitResult::Rejected
+                self.record_event(CircuitResult::Rejected);
+                false
             }
             CircuitState::HalfOpen => {
                 assert!(self.closed_failures == self.closed_failures_threshold);
                 assert!(self.half_open_attempts < self.half_open_threshold);
+                assert!(self.half_open_successes < self.half_open_success_threshold);
+                true
+            }
+        }
+    }
+
+    fn record_success(&mut self) {
+        if self.disabled {
+            return;
+        }
+
+        match self.state {
+            CircuitState::Closed => {
+                self.metrics.succeeded += 1;
+                self.push_outcome(false);
+            }
+            CircuitState::HalfOpen => {
+                self.metrics.succeeded += 1;
+                self.half_open_successes += 1;
+                if self.half_open_successes == self.half_open_success_threshold {
+                    self.transition(CircuitState::Closed);
+                    self.close();
+                }
+            }
+            CircuitState::Open => return,
+        }
+        self.record_event(CircuitResult::Succeeded);
+    }
+
+    fn record_failure(&mut self) {
+        if self.disabled {
+            return;
+        }
 
-                let result = f();
-                match result {
-                    Ok(_) => {
-                        self.state = CircuitState::Closed;
-                        self.closed_failures = 0;
-                        CircuitResult::Succeeded
-                    }
-                    Err(_) => {
-                        self.state = CircuitState::Open;
-                        self.half_open_attempts = 0;
-                        CircuitResult::Failed
-                    }
+        match self.state {
+            CircuitState::Closed => {
+                self.metrics.failed += 1;
+                self.push_outcome(true);
+                if self.closed_failures == self.closed_failures_threshold {
+                    self.transition(CircuitState::Open);
+                    self.clear_outcomes();
                 }
             }
+            CircuitState::HalfOpen => {
+                self.metrics.failed += 1;
+                self.half_open_successes = 0;
+                if self.tolerate_half_open_failures {
+                    self.half_open_attempts += 1;
+                }
+                if !self.tolerate_half_open_failures
+                    || self.half_open_attempts == self.half_open_threshold
+                {
+                    self.transition(CircuitState::Open);
+                    self.half_open_attempts = 0;
+                }
+            }
+            CircuitState::Open => return,
         }
+        self.record_event(CircuitResult::Failed);
     }
 
     fn state(&self) -> CircuitState {
         self.state
     }
+
+    fn is_call_permitted(&self) -> bool {
+        if self.disabled {
+            return true;
+        }
+        self.state != CircuitState::Open
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn reset(&mut self) {
+        if self.state != CircuitState::Closed {
+            self.transition(CircuitState::Closed);
+        }
+        self.close();
+    }
+
+    fn trip(&mut self) {
+        if self.state != CircuitState::Open {
+            self.transition(CircuitState::Open);
+        }
+        self.closed_failures = self.closed_failures_threshold;
+        self.clear_outcomes();
+        self.half_open_attempts = 0;
+        self.half_open_successes = 0;
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
+    use crate::cb::CircuitResult;
+    use std::sync::mpsc;
 
     #[test]
     #[should_panic]
@@ -110,23 +541,256 @@ mod tests {
         CountCB::new(0, 0);
     }
 
+    #[test]
+    fn test_presets_trip_at_documented_failure_count() {
+        let presets = [
+            (CountCB::sensitive(), 3),
+            (CountCB::balanced(), 5),
+            (CountCB::tolerant(), 10),
+        ];
+
+        for (mut cb, failure_threshold) in presets {
+            for _ in 0..failure_threshold - 1 {
+                let result = cb.call(|| Err::<(), ()>(()));
+                assert_eq!(result, Err(CircuitError::Failed(())));
+                assert_eq!(cb.state(), CircuitState::Closed);
+            }
+
+            let result = cb.call(|| Err::<(), ()>(()));
+            assert_eq!(result, Err(CircuitError::Failed(())));
+            assert_eq!(cb.state(), CircuitState::Open);
+        }
+    }
+
+    #[test]
+    fn test_presets_probe_after_documented_rejections() {
+        let presets = [
+            (CountCB::sensitive(), 3, 10),
+            (CountCB::balanced(), 5, 5),
+            (CountCB::tolerant(), 10, 3),
+        ];
+
+        for (mut cb, failure_threshold, half_open_threshold) in presets {
+            for _ in 0..failure_threshold {
+                let _ = cb.call(|| Err::<(), ()>(()));
+            }
+            assert_eq!(cb.state(), CircuitState::Open);
+
+            for _ in 0..half_open_threshold - 1 {
+                let result = cb.call(|| Ok::<(), ()>(()));
+                assert_eq!(result, Err(CircuitError::Rejected));
+                assert_eq!(cb.state(), CircuitState::Open);
+            }
+
+            let result = cb.call(|| Ok::<(), ()>(()));
+            assert_eq!(result, Err(CircuitError::Rejected));
+            assert_eq!(cb.state(), CircuitState::HalfOpen);
+        }
+    }
+
+    #[test]
+    fn test_failures_until_trip_counts_down_to_open() {
+        let mut cb = CountCB::new(3, 1);
+        assert_eq!(cb.failures_until_trip(), 3);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 2);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 1);
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 0);
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.failures_until_trip(), 0);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failures_until_trip(), 3);
+    }
+
+    #[test]
+    fn test_failures_until_trip_resets_on_success() {
+        let mut cb = CountCB::new(3, 1);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 1);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 3);
+    }
+
+    #[test]
+    fn test_reset_from_open_executes_next_call() {
+        let mut cb = CountCB::new(2, 3);
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        cb.reset();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failures_until_trip(), 2);
+
+        let mut executed = false;
+        let result = cb.call(|| {
+            executed = true;
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert!(executed);
+    }
+
+    #[test]
+    fn test_reset_from_half_open() {
+        let mut cb = CountCB::new(1, 1);
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        cb.reset();
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_reset_is_idempotent_when_closed() {
+        let mut cb = CountCB::new(3, 1);
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 2);
+
+        cb.reset();
+        cb.reset();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failures_until_trip(), 3);
+    }
+
+    #[test]
+    fn test_trip_rejects_then_recovers() {
+        let mut cb = CountCB::new(3, 2);
+        cb.trip();
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.failures_until_trip(), 0);
+
+        let mut executed = false;
+        let result = cb.call(|| {
+            executed = true;
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert!(!executed);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_trip_from_half_open() {
+        let mut cb = CountCB::new(1, 1);
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        cb.trip();
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn test_trip_probability() {
+        let cases = [
+            (1, 0.5, 1, 0.5),
+            (1, 0.5, 2, 0.75),
+            (2, 0.5, 1, 0.0),
+            (2, 0.5, 2, 0.25),
+            (2, 0.5, 3, 0.375),
+            (2, 0.5, 4, 0.5),
+            (3, 0.1, 3, 0.001),
+            (3, 0.0, 100, 0.0),
+            (3, 1.0, 3, 1.0),
+            (3, 1.0, 2, 0.0),
+        ];
+
+        for (failure_threshold, p_fail, calls, want) in cases {
+            let got = trip_probability(failure_threshold, p_fail, calls);
+            assert!(
+                (got - want).abs() < 1e-12,
+                "threshold {failure_threshold}, p {p_fail}, calls {calls}: got {got}, want {want}"
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_trip_probability_invalid_p_panics() {
+        trip_probability(1, 1.5, 1);
+    }
+
     #[test]
     fn test_closed_success() {
         let mut cb = CountCB::new(2, 1);
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Succeeded);
+        assert_eq!(result, Ok(()));
         assert_eq!(cb.state(), CircuitState::Closed);
     }
 
+    #[test]
+    fn test_call_returns_closure_value() {
+        let mut cb = CountCB::new(1, 1);
+
+        let result = cb.call(|| Ok::<u32, ()>(42));
+        assert_eq!(result, Ok(42));
+        assert_eq!(CircuitResult::from(&result), CircuitResult::Succeeded);
+
+        let result = cb.call(|| Err::<u32, ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(CircuitResult::from(&result), CircuitResult::Failed);
+
+        let result = cb.call(|| Ok::<u32, ()>(42));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(CircuitResult::from(&result), CircuitResult::Rejected);
+    }
+
+    #[test]
+    fn test_call_preserves_closure_error() {
+        let mut cb = CountCB::new(1, 1);
+
+        let result = cb.call(|| "42".parse::<u32>());
+        assert_eq!(result, Ok(42));
+
+        let result = cb.call(|| "forty-two".parse::<u32>());
+        assert!(
+            matches!(result, Err(CircuitError::Failed(ref e)) if e.to_string() == "invalid digit found in string")
+        );
+
+        let result = cb.call(|| "42".parse::<u32>());
+        assert!(matches!(result, Err(CircuitError::Rejected)));
+    }
+
     #[test]
     fn test_closed_failure_stays_closed() {
         let mut cb = CountCB::new(2, 1);
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Closed);
     }
 
@@ -136,11 +800,11 @@ mod tests {
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Open);
     }
 
@@ -150,15 +814,15 @@ mod tests {
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Open);
 
         let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Rejected);
+        assert_eq!(result, Err(CircuitError::Rejected));
         assert_eq!(cb.state(), CircuitState::Open);
     }
 
@@ -168,15 +832,15 @@ mod tests {
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Open);
 
         let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Rejected);
+        assert_eq!(result, Err(CircuitError::Rejected));
         assert_eq!(cb.state(), CircuitState::HalfOpen);
     }
 
@@ -186,19 +850,19 @@ mod tests {
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Open);
 
         let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Rejected);
+        assert_eq!(result, Err(CircuitError::Rejected));
         assert_eq!(cb.state(), CircuitState::HalfOpen);
 
         let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Succeeded);
+        assert_eq!(result, Ok(()));
         assert_eq!(cb.state(), CircuitState::Closed);
     }
 
@@ -208,19 +872,459 @@ mod tests {
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Closed);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
         assert_eq!(cb.state(), CircuitState::Open);
 
         let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Rejected);
+        assert_eq!(result, Err(CircuitError::Rejected));
         assert_eq!(cb.state(), CircuitState::HalfOpen);
 
         let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log_records_on_trip_and_recovery() {
+        crate::cb::test_logger::install();
+        crate::cb::test_logger::take();
+        let mut cb = CountCB::new(1, 1).name("payments");
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+
+        assert_eq!(
+            crate::cb::test_logger::take(),
+            vec![
+                (
+                    log::Level::Warn,
+                    "circuit breaker payments: closed -> open".to_string()
+                ),
+                (
+                    log::Level::Info,
+                    "circuit breaker payments: open -> half-open".to_string()
+                ),
+                (
+                    log::Level::Info,
+                    "circuit breaker payments: half-open -> closed".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transition_sender_receives_transitions_in_order() {
+        let (tx, rx) = mpsc::channel();
+        let mut cb = CountCB::new(1, 1).transition_sender(tx);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+
+        let transitions: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            transitions,
+            vec![
+                (CircuitState::Closed, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Closed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transition_sender_dropped_receiver() {
+        let (tx, rx) = mpsc::channel();
+        let mut cb = CountCB::new(1, 1).transition_sender(tx);
+        drop(rx);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_version_bumps_only_on_transitions() {
+        let mut cb = CountCB::new(2, 1);
+        assert_eq!(cb.version(), 0);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.version(), 0);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.version(), 1);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.version(), 2);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.version(), 3);
+
+        cb.reset();
+        assert_eq!(cb.version(), 3);
+        cb.trip();
+        cb.trip();
+        assert_eq!(cb.version(), 4);
+    }
+
+    #[test]
+    fn test_half_open_success_threshold_requires_consecutive_successes() {
+        let mut cb = CountCB::new(1, 1).half_open_success_threshold(3);
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        for _ in 0..2 {
+            assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+            assert_eq!(cb.state(), CircuitState::HalfOpen);
+        }
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_tolerate_half_open_failures_reopens_at_threshold() {
+        let mut cb = CountCB::new(1, 3).tolerate_half_open_failures();
+        let _ = cb.call(|| Err::<(), ()>(()));
+        for _ in 0..3 {
+            let _ = cb.call(|| Ok::<(), ()>(()));
+        }
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        for _ in 0..2 {
+            assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+            assert_eq!(cb.state(), CircuitState::HalfOpen);
+        }
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_tolerate_half_open_failures_closes_on_success() {
+        let mut cb = CountCB::new(1, 2).tolerate_half_open_failures();
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_metrics_after_known_sequence() {
+        let mut cb = CountCB::new(2, 2);
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        cb.trip();
+
+        assert_eq!(
+            cb.metrics(),
+            Metrics {
+                succeeded: 1,
+                failed: 3,
+                rejected: 2,
+                opens: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_on_transition_records_each_transition_once() {
+        let transitions = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = transitions.clone();
+        let mut cb =
+            CountCB::new(1, 1).on_transition(move |from, to| sink.borrow_mut().push((from, to)));
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+
+        assert_eq!(
+            *transitions.borrow(),
+            vec![
+                (CircuitState::Closed, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Closed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_call_permitted_matches_call() {
+        let mut cb = CountCB::new(1, 2);
+        let outcomes = [
+            Err(()),
+            Ok(()),
+            Ok(()),
+            Ok(()),
+            Err(()),
+            Ok(()),
+            Ok(()),
+            Ok(()),
+        ];
+        for outcome in outcomes {
+            let permitted = cb.is_call_permitted();
+            let state = cb.state();
+            assert_eq!(cb.is_call_permitted(), permitted);
+            assert_eq!(cb.state(), state);
+
+            let mut executed = false;
+            let _ = cb.call(|| {
+                executed = true;
+                outcome
+            });
+            assert_eq!(executed, permitted);
+        }
+    }
+
+    #[test]
+    fn test_disabled_ignores_long_failure_run() {
+        let mut cb = CountCB::new(2, 1);
+        cb.disable();
+        for _ in 0..100 {
+            assert_eq!(cb.call(|| Err::<(), u8>(7)), Err(CircuitError::Failed(7)));
+        }
+        assert_eq!(cb.call(|| Ok::<u8, ()>(1)), Ok(1));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failures_until_trip(), 2);
+        assert_eq!(cb.metrics(), Metrics::default());
+    }
+
+    #[test]
+    fn test_enable_starts_from_closed() {
+        let mut cb = CountCB::new(1, 5);
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+        cb.disable();
+        assert!(cb.is_call_permitted());
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        cb.enable();
+        assert!(!cb.is_disabled());
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+    }
+
+    #[test]
+    fn test_call_classified_ignored_errors_never_open() {
+        let mut cb = CountCB::new(2, 1);
+        for _ in 0..100 {
+            let result = cb.call_classified(|| Err::<(), u16>(404), |status| *status >= 500);
+            assert_eq!(result, Err(CircuitError::Failed(404)));
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failures_until_trip(), 2);
+
+        let _ = cb.call_classified(|| Err::<(), u16>(503), |status| *status >= 500);
+        let _ = cb.call_classified(|| Err::<(), u16>(404), |status| *status >= 500);
+        assert_eq!(cb.failures_until_trip(), 1);
+        let _ = cb.call_classified(|| Err::<(), u16>(503), |status| *status >= 500);
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let _ = cb.call(|| Ok::<(), u16>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        let _ = cb.call_classified(|| Err::<(), u16>(404), |status| *status >= 500);
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn test_acquire_record_trips_and_recovers() {
+        let mut cb = CountCB::new(2, 2);
+        assert!(cb.try_acquire());
+        cb.record_failure();
+        assert!(cb.try_acquire());
+        cb.record_success();
+        assert_eq!(cb.failures_until_trip(), 2);
+
+        for _ in 0..2 {
+            assert!(cb.try_acquire());
+            cb.record_failure();
+        }
         assert_eq!(cb.state(), CircuitState::Open);
+
+        assert!(!cb.try_acquire());
+        assert!(!cb.try_acquire());
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(cb.try_acquire());
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.metrics().rejected, 2);
+    }
+
+    #[test]
+    fn test_acquire_record_half_open_failure_reopens() {
+        let mut cb = CountCB::new(1, 1);
+        assert!(cb.try_acquire());
+        cb.record_failure();
+        assert!(!cb.try_acquire());
+        assert!(cb.try_acquire());
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_failure_count_tracks_failures_and_resets_on_success() {
+        let mut cb = CountCB::new(10, 3);
+        for expected in 1..=7 {
+            let _ = cb.call(|| Err::<(), ()>(()));
+            assert_eq!(cb.failure_count(), expected);
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.failure_count(), 0);
+        assert_eq!(cb.half_open_attempts(), 0);
+
+        cb.trip();
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.failure_count(), 10);
+        assert_eq!(cb.half_open_attempts(), 2);
+    }
+
+    #[test]
+    fn test_threshold_above_u8_opens_at_exactly_threshold() {
+        let mut cb = CountCB::new(1000, 1);
+        for _ in 0..999 {
+            let _ = cb.call(|| Err::<(), ()>(()));
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failure_count(), 999);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_event_log_keeps_last_events_in_sequence() {
+        let mut cb = CountCB::new(2, 2).event_log(3);
+        assert_eq!(cb.events().count(), 0);
+        for succeed in [true, false, false, true, true, true] {
+            let _ = cb.call(|| if succeed { Ok(()) } else { Err(()) });
+            assert!(cb.events().count() <= 3);
+        }
+
+        let events: Vec<_> = cb.events().cloned().collect();
+        assert_eq!(
+            events,
+            vec![
+                Event {
+                    at: 3,
+                    result: CircuitResult::Rejected,
+                    state: CircuitState::Open,
+                },
+                Event {
+                    at: 4,
+                    result: CircuitResult::Rejected,
+                    state: CircuitState::HalfOpen,
+                },
+                Event {
+                    at: 5,
+                    result: CircuitResult::Succeeded,
+                    state: CircuitState::Closed,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_failure_window_below_threshold_panics() {
+        let _ = CountCB::new(3, 1).failure_window(2);
+    }
+
+    #[test]
+    fn test_failure_window_counts_interleaved_failures() {
+        let pattern = [false, true, false, true, false];
+        let mut consecutive = CountCB::new(3, 1);
+        let mut windowed = CountCB::new(3, 1).failure_window(5);
+
+        for succeed in pattern {
+            let _ = consecutive.call(|| if succeed { Ok(()) } else { Err(()) });
+            let _ = windowed.call(|| if succeed { Ok(()) } else { Err(()) });
+        }
+        assert_eq!(consecutive.state(), CircuitState::Closed);
+        assert_eq!(consecutive.failure_count(), 1);
+        assert_eq!(windowed.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_failure_window_forgets_old_failures() {
+        let mut cb = CountCB::new(2, 1).failure_window(3);
+        for succeed in [false, true, true, true, false] {
+            let _ = cb.call(|| if succeed { Ok(()) } else { Err(()) });
+        }
+        assert_eq!(cb.failure_count(), 1);
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failure_count(), 0);
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_try_new_reports_each_zero_threshold() {
+        assert_eq!(
+            CountCB::try_new(0, 1).err(),
+            Some(BuildError::ZeroFailureThreshold)
+        );
+        assert_eq!(
+            CountCB::try_new(1, 0).err(),
+            Some(BuildError::ZeroHalfOpenThreshold)
+        );
+        assert!(CountCB::try_new(1, 1).is_ok());
     }
 }
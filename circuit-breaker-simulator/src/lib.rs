@@ -1,10 +1,52 @@
 //! Circuit Breaker Simulator Library
+//!
+//! Without the default `std` feature the crate is `no_std`: the `CircuitBreaker`
+//! trait, `CountCB` and `CircuitService` build on `core` alone, while `TimeCB` and
+//! everything built on a clock, a lock, a hash map or `rand` is left out. The
+//! `alloc` feature adds `RateCB`, `SlidingWindowCB`, and `CountCB`'s callbacks,
+//! event log and failure window.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod builder;
 pub mod cb;
+#[cfg(feature = "std")]
+pub mod config;
 pub mod count;
+pub mod random;
+#[cfg(feature = "alloc")]
+pub mod rate;
+#[cfg(feature = "std")]
+pub mod registry;
+pub mod service;
+#[cfg(feature = "std")]
 pub mod sim;
+#[cfg(feature = "std")]
+pub mod sync;
+#[cfg(feature = "std")]
 pub mod time;
+#[cfg(feature = "alloc")]
+pub mod window;
+#[cfg(feature = "alloc")]
+mod windowed;
 
+pub use builder::*;
 pub use cb::*;
+#[cfg(feature = "std")]
+pub use config::*;
 pub use count::*;
+pub use random::*;
+#[cfg(feature = "alloc")]
+pub use rate::*;
+#[cfg(feature = "std")]
+pub use registry::*;
+pub use service::*;
+#[cfg(feature = "std")]
+pub use sync::*;
+#[cfg(feature = "std")]
 pub use time::*;
+#[cfg(feature = "alloc")]
+pub use window::*;
@@ -0,0 +1,17 @@
+//! Injectable randomness, so breakers stay deterministic under test.
+
+#[cfg(feature = "std")]
+use rand::{rngs::StdRng, Rng};
+
+/// Source of uniform random numbers, in the spirit of `Clock`.
+pub trait RandomSource {
+    /// Returns a value in `[0, 1)`.
+    fn next_f64(&mut self) -> f64;
+}
+
+#[cfg(feature = "std")]
+impl RandomSource for StdRng {
+    fn next_f64(&mut self) -> f64 {
+        self.random::<f64>()
+    }
+}
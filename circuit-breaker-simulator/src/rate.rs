@@ -0,0 +1,286 @@
+//! Failure-rate Circuit Breaker implementation
+
+use crate::cb::{CircuitBreaker, CircuitError, CircuitState};
+use crate::windowed::{TripCondition, WindowedCB};
+
+/// Opens when at least `failure_rate_percent` of the last `window_size` calls
+/// failed, once the window holds at least `min_calls` calls. `min_calls` is the
+/// breaker's minimum throughput: below it no failure rate can open the breaker.
+///
+/// Open and half-open behave like `CountCB`: `half_open_threshold` rejected calls
+/// lead to a single probe. A successful probe closes the breaker with an empty
+/// window, so after recovery `min_calls` calls must be seen again before the rate
+/// can reopen it; a failed probe reopens it immediately.
+#[derive(Debug)]
+pub struct RateCB {
+    inner: WindowedCB<FailureRate>,
+}
+
+#[derive(Debug)]
+struct FailureRate {
+    failure_rate_percent: u8,
+    min_calls: u32,
+}
+
+impl TripCondition for FailureRate {
+    fn reached(&self, calls: u32, failures: u32) -> bool {
+        calls >= self.min_calls
+            && u64::from(failures) * 100 >= u64::from(self.failure_rate_percent) * u64::from(calls)
+    }
+}
+
+impl RateCB {
+    pub fn new(
+        window_size: u32,
+        failure_rate_percent: u8,
+        min_calls: u32,
+        half_open_threshold: u32,
+    ) -> Self {
+        assert!(failure_rate_percent > 0);
+        assert!(failure_rate_percent <= 100);
+        assert!(min_calls > 0);
+        assert!(min_calls <= window_size);
+
+        let trip = FailureRate {
+            failure_rate_percent,
+            min_calls,
+        };
+        RateCB {
+            inner: WindowedCB::new(window_size, trip, half_open_threshold),
+        }
+    }
+}
+
+impl CircuitBreaker for RateCB {
+    fn call_classified<F, R, E, P>(
+        &mut self,
+        f: F,
+        should_count_failure: P,
+    ) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+        P: FnOnce(&E) -> bool,
+    {
+        self.inner.call_classified(f, should_count_failure)
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        self.inner.try_acquire()
+    }
+
+    fn record_success(&mut self) {
+        self.inner.record_success();
+    }
+
+    fn record_failure(&mut self) {
+        self.inner.record_failure();
+    }
+
+    fn state(&self) -> CircuitState {
+        self.inner.state()
+    }
+
+    fn is_call_permitted(&self) -> bool {
+        self.inner.is_call_permitted()
+    }
+
+    fn version(&self) -> u64 {
+        self.inner.version()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn trip(&mut self) {
+        self.inner.trip();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    fn drive(cb: &mut RateCB, outcomes: &[bool]) -> Vec<CircuitState> {
+        outcomes
+            .iter()
+            .map(|succeed| {
+                let _ = cb.call(|| if *succeed { Ok(()) } else { Err(()) });
+                cb.state()
+            })
+            .collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_window_size_panics() {
+        RateCB::new(0, 50, 1, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_rate_panics() {
+        RateCB::new(10, 0, 1, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rate_above_hundred_panics() {
+        RateCB::new(10, 101, 1, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_min_calls_panics() {
+        RateCB::new(10, 50, 0, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_min_calls_above_window_panics() {
+        RateCB::new(10, 50, 11, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_half_open_threshold_panics() {
+        RateCB::new(10, 50, 1, 0);
+    }
+
+    #[test]
+    fn test_min_calls_holds_back_early_failures() {
+        let mut cb = RateCB::new(10, 50, 4, 1);
+
+        let states = drive(&mut cb, &[false, false, true, true]);
+        assert_eq!(
+            states,
+            vec![
+                CircuitState::Closed,
+                CircuitState::Closed,
+                CircuitState::Closed,
+                CircuitState::Open,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_below_rate_stays_closed() {
+        let mut cb = RateCB::new(10, 50, 4, 1);
+
+        let states = drive(
+            &mut cb,
+            &[false, true, true, true, false, true, true, false, false],
+        );
+        assert!(states.iter().all(|state| *state == CircuitState::Closed));
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_window_evicts_old_calls() {
+        let mut cb = RateCB::new(4, 75, 4, 1);
+
+        let states = drive(&mut cb, &[false, false, true, true, true, false, false]);
+        assert!(states.iter().all(|state| *state == CircuitState::Closed));
+
+        let states = drive(&mut cb, &[false]);
+        assert_eq!(states, vec![CircuitState::Open]);
+    }
+
+    #[test]
+    fn test_recovery_requires_min_calls_again() {
+        let mut cb = RateCB::new(10, 50, 3, 1);
+        let _ = drive(&mut cb, &[false, false, false]);
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let states = drive(&mut cb, &[false, false, false]);
+        assert_eq!(
+            states,
+            vec![
+                CircuitState::Closed,
+                CircuitState::Closed,
+                CircuitState::Open,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_half_open_failure_reopens() {
+        let mut cb = RateCB::new(10, 50, 1, 1);
+        let _ = drive(&mut cb, &[false]);
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_acquire_record_recovery_requires_min_calls_again() {
+        let mut cb = RateCB::new(10, 50, 3, 1);
+        for _ in 0..3 {
+            assert!(cb.try_acquire());
+            cb.record_failure();
+        }
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        assert!(!cb.try_acquire());
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(cb.try_acquire());
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        for expected in [
+            CircuitState::Closed,
+            CircuitState::Closed,
+            CircuitState::Open,
+        ] {
+            assert!(cb.try_acquire());
+            cb.record_failure();
+            assert_eq!(cb.state(), expected);
+        }
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_single_failure_below_min_calls_stays_closed() {
+        let mut cb = RateCB::new(20, 50, 20, 1);
+
+        let states = drive(&mut cb, &[false]);
+        assert_eq!(states, vec![CircuitState::Closed]);
+
+        let states = drive(&mut cb, &[false; 9]);
+        assert!(states.iter().all(|state| *state == CircuitState::Closed));
+
+        let mut outcomes = vec![true; 9];
+        outcomes.push(false);
+        let states = drive(&mut cb, &outcomes);
+        assert_eq!(states.last(), Some(&CircuitState::Open));
+    }
+
+    #[test]
+    fn test_window_above_u8_opens_at_min_calls() {
+        let mut cb = RateCB::new(1000, 50, 1000, 1);
+        let mut outcomes = vec![true; 500];
+        outcomes.extend([false; 499]);
+        let states = drive(&mut cb, &outcomes);
+        assert!(states.iter().all(|state| *state == CircuitState::Closed));
+
+        let states = drive(&mut cb, &[false]);
+        assert_eq!(states, vec![CircuitState::Open]);
+    }
+}
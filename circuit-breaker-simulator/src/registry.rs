@@ -0,0 +1,109 @@
+//! One circuit breaker per key, created on first use
+
+use crate::cb::{CircuitBreaker, CircuitError, CircuitState};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Routes each call to the breaker of its key, e.g. one breaker per downstream
+/// host. Breakers are built by `factory` the first time a key is called, so every
+/// key starts from the same template.
+pub struct CircuitBreakerRegistry<K, B> {
+    breakers: HashMap<K, B>,
+    factory: Box<dyn Fn() -> B + Send>,
+}
+
+impl<K: Eq + Hash, B: CircuitBreaker> CircuitBreakerRegistry<K, B> {
+    pub fn new(factory: impl Fn() -> B + Send + 'static) -> Self {
+        CircuitBreakerRegistry {
+            breakers: HashMap::new(),
+            factory: Box::new(factory),
+        }
+    }
+
+    pub fn call<F, R, E>(&mut self, key: K, f: F) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+    {
+        self.breakers
+            .entry(key)
+            .or_insert_with(&self.factory)
+            .call(f)
+    }
+
+    /// `None` for a key that has never been called.
+    pub fn state(&self, key: &K) -> Option<CircuitState> {
+        self.breakers.get(key).map(|breaker| breaker.state())
+    }
+
+    pub fn get(&self, key: &K) -> Option<&B> {
+        self.breakers.get(key)
+    }
+
+    /// Every key seen so far with its breaker's state, in no particular order.
+    pub fn states(&self) -> impl Iterator<Item = (&K, CircuitState)> {
+        self.breakers
+            .iter()
+            .map(|(key, breaker)| (key, breaker.state()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.breakers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.breakers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CbConfig;
+    use crate::count::CountCB;
+    use std::time::Duration;
+
+    fn registry() -> CircuitBreakerRegistry<&'static str, CountCB> {
+        let config = CbConfig {
+            failure_threshold: 2,
+            half_open_threshold: 1,
+            open_timeout: Duration::from_secs(1),
+        };
+        CircuitBreakerRegistry::new(move || config.count_cb())
+    }
+
+    #[test]
+    fn test_keys_keep_independent_states() {
+        let mut registry = registry();
+        for _ in 0..2 {
+            let _ = registry.call("a", || Err::<(), ()>(()));
+        }
+        let result = registry.call("b", || Ok::<u8, ()>(1));
+        assert_eq!(result, Ok(1));
+
+        assert_eq!(registry.state(&"a"), Some(CircuitState::Open));
+        assert_eq!(registry.state(&"b"), Some(CircuitState::Closed));
+        assert_eq!(
+            registry.call("a", || Ok::<(), ()>(())),
+            Err(CircuitError::Rejected)
+        );
+
+        let mut states: Vec<_> = registry.states().collect();
+        states.sort_by_key(|(key, _)| **key);
+        assert_eq!(
+            states,
+            vec![(&"a", CircuitState::HalfOpen), (&"b", CircuitState::Closed)]
+        );
+    }
+
+    #[test]
+    fn test_unknown_key_gets_fresh_closed_breaker() {
+        let mut registry = registry();
+        assert!(registry.is_empty());
+        assert_eq!(registry.state(&"new"), None);
+
+        let _ = registry.call("new", || Err::<(), ()>(()));
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.state(&"new"), Some(CircuitState::Closed));
+        assert_eq!(registry.get(&"new").unwrap().failures_until_trip(), 1);
+    }
+}
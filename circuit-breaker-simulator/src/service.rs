@@ -0,0 +1,126 @@
+//! Service-shaped adapter over a circuit breaker
+
+use crate::cb::{CircuitBreaker, CircuitError};
+use core::task::Poll;
+
+/// Minimal request/response service shaped after `tower::Service`, without the
+/// async machinery, so the crate stays framework-agnostic.
+pub trait Service<Request> {
+    type Response;
+    type Error;
+
+    fn poll_ready(&mut self) -> Poll<Result<(), Self::Error>>;
+
+    fn call(&mut self, request: Request) -> Result<Self::Response, Self::Error>;
+}
+
+/// Runs every request to `inner` through `breaker`.
+///
+/// Unlike tower, an error from `poll_ready` is not terminal: an open breaker only
+/// makes progress towards `HalfOpen` when it sees calls, so `call` stays usable
+/// and returns `CircuitError::Rejected` while the breaker is open.
+#[derive(Debug)]
+pub struct CircuitService<B, S> {
+    breaker: B,
+    inner: S,
+}
+
+impl<B: CircuitBreaker, S> CircuitService<B, S> {
+    pub fn new(breaker: B, inner: S) -> Self {
+        Self { breaker, inner }
+    }
+
+    pub fn breaker(&self) -> &B {
+        &self.breaker
+    }
+}
+
+impl<B, S, Request, Response, E> Service<Request> for CircuitService<B, S>
+where
+    B: CircuitBreaker,
+    S: FnMut(Request) -> Result<Response, E>,
+{
+    type Response = Response;
+    type Error = CircuitError<E>;
+
+    fn poll_ready(&mut self) -> Poll<Result<(), Self::Error>> {
+        if self.breaker.is_call_permitted() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Ready(Err(CircuitError::Rejected))
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Result<Self::Response, Self::Error> {
+        let inner = &mut self.inner;
+        self.breaker.call(|| inner(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cb::CircuitState;
+    use crate::count::CountCB;
+
+    fn double(request: u32) -> Result<u32, &'static str> {
+        if request == 0 {
+            Err("zero")
+        } else {
+            Ok(request * 2)
+        }
+    }
+
+    #[test]
+    fn test_poll_ready_when_closed() {
+        let mut service = CircuitService::new(CountCB::new(2, 1), double);
+        assert_eq!(service.poll_ready(), Poll::Ready(Ok(())));
+
+        assert_eq!(service.call(21), Ok(42));
+        assert_eq!(service.poll_ready(), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn test_poll_ready_reflects_breaker_state() {
+        let mut service = CircuitService::new(CountCB::new(2, 1), double);
+
+        assert_eq!(service.call(0), Err(CircuitError::Failed("zero")));
+        assert_eq!(service.poll_ready(), Poll::Ready(Ok(())));
+
+        assert_eq!(service.call(0), Err(CircuitError::Failed("zero")));
+        assert_eq!(service.breaker().state(), CircuitState::Open);
+        assert_eq!(
+            service.poll_ready(),
+            Poll::Ready(Err(CircuitError::Rejected))
+        );
+
+        assert_eq!(service.call(1), Err(CircuitError::Rejected));
+        assert_eq!(service.breaker().state(), CircuitState::HalfOpen);
+        assert_eq!(service.poll_ready(), Poll::Ready(Ok(())));
+
+        assert_eq!(service.call(1), Ok(2));
+        assert_eq!(service.breaker().state(), CircuitState::Closed);
+        assert_eq!(service.poll_ready(), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_poll_ready_once_open_timeout_elapsed() {
+        use crate::time::{LogicalClock, TimeCB};
+        use std::time::Duration;
+
+        let cb = TimeCB::with_clock(Duration::from_millis(5), 1, 1, LogicalClock::new());
+        let mut service = CircuitService::new(cb, double);
+        assert_eq!(service.call(0), Err(CircuitError::Failed("zero")));
+        assert_eq!(
+            service.poll_ready(),
+            Poll::Ready(Err(CircuitError::Rejected))
+        );
+
+        service.breaker().clock().advance(Duration::from_millis(5));
+        assert_eq!(service.breaker().state(), CircuitState::Open);
+        assert_eq!(service.poll_ready(), Poll::Ready(Ok(())));
+        assert_eq!(service.call(1), Ok(2));
+        assert_eq!(service.breaker().state(), CircuitState::Closed);
+    }
+}
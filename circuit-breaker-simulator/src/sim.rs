@@ -1,140 +1,234 @@
 //! Simulation harness for circuit breakers
 
-#[cfg(test)]
-mod tests {
-    use crate::cb::CircuitBreaker;
-    use crate::count::CountCB;
-    use crate::time::{Clock, TimeCB};
-    use rand::rngs::StdRng;
-    use rand::{Rng, SeedableRng};
-    use std::cell::Cell;
-    use std::rc::Rc;
-    use std::time::{Duration, Instant};
-
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    enum StepCount {
-        Success,
-        Failure,
-    }
+use crate::cb::{CircuitBreaker, CircuitResult};
+use crate::count::CountCB;
+use crate::time::{LogicalClock, TimeCB};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+
+/// Step for breakers driven only by call outcomes
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum StepCount {
+    Success,
+    Failure,
+}
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    enum StepTime {
-        Success,
-        Failure,
-        Tick,
-    }
+/// Step for breakers driven by call outcomes and the passage of time
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum StepTime {
+    Success,
+    Failure,
+    Tick,
+}
 
-    #[derive(Debug, Clone)]
-    struct TestClock {
-        now: Rc<Cell<Instant>>,
+/// `count` uniformly random `StepCount`s; the same seed always gives the same steps.
+pub fn random_steps_count(seed: u64, count: usize) -> Vec<StepCount> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut steps = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let choice = if rng.random_range(0..2) == 0 {
+            StepCount::Success
+        } else {
+            StepCount::Failure
+        };
+        steps.push(choice);
     }
 
-    impl Clock for TestClock {
-        fn now(&self) -> Instant {
-            self.now.get()
-        }
+    steps
+}
+
+/// `count` uniformly random `StepTime`s; the same seed always gives the same steps.
+pub fn random_steps_time(seed: u64, count: usize) -> Vec<StepTime> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut steps = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let choice = rng.random_range(0..3);
+        steps.push(match choice {
+            0 => StepTime::Success,
+            1 => StepTime::Failure,
+            _ => StepTime::Tick,
+        });
     }
 
-    impl TestClock {
-        const TICK: Duration = Duration::from_millis(1);
+    steps
+}
 
-        fn new(start: Instant) -> Self {
-            Self {
-                now: Rc::new(Cell::new(start)),
-            }
-        }
+/// Feeds every step to `cb` as a call and returns what each call did.
+pub fn run_simulation<B: CircuitBreaker>(cb: &mut B, steps: &[StepCount]) -> Vec<CircuitResult> {
+    steps.iter().map(|step| call_step(cb, step)).collect()
+}
 
-        fn tick(&self) {
-            self.now.set(self.now.get() + Self::TICK);
-        }
-    }
+/// Like `run_simulation`, but a panic inside the breaker, i.e. a broken invariant,
+/// is re-raised naming the step that caused it.
+///
+/// ```
+/// use circuit_breaker_simulator::sim;
+/// use circuit_breaker_simulator::CountCB;
+///
+/// let mut cb = CountCB::new(3, 2);
+/// let steps = sim::random_steps_count(7, 1_000);
+/// let results = sim::run_checked(&mut cb, &steps);
+/// assert_eq!(results.len(), steps.len());
+/// ```
+pub fn run_checked<B: CircuitBreaker>(cb: &mut B, steps: &[StepCount]) -> Vec<CircuitResult> {
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| checked(i, step, || call_step(cb, step)))
+        .collect()
+}
 
-    fn generate_random_steps_count(seed: u64, count: usize) -> Vec<StepCount> {
-        let mut rng = StdRng::seed_from_u64(seed);
-        let mut steps = Vec::with_capacity(count);
-
-        for _ in 0..count {
-            let choice = if rng.random_range(0..2) == 0 {
-                StepCount::Success
-            } else {
-                StepCount::Failure
-            };
-            steps.push(choice);
-        }
+/// Feeds `steps` to `cb`, calling `tick` for every `StepTime::Tick` to advance
+/// whatever clock drives the breaker. Returns what each call did; ticks have no
+/// entry.
+pub fn run_simulation_timed<B: CircuitBreaker>(
+    cb: &mut B,
+    steps: &[StepTime],
+    mut tick: impl FnMut(&mut B),
+) -> Vec<CircuitResult> {
+    steps
+        .iter()
+        .filter_map(|step| time_step(cb, step, &mut tick))
+        .collect()
+}
 
-        steps
-    }
+/// `run_checked` for time-driven steps.
+pub fn run_checked_timed<B: CircuitBreaker>(
+    cb: &mut B,
+    steps: &[StepTime],
+    mut tick: impl FnMut(&mut B),
+) -> Vec<CircuitResult> {
+    steps
+        .iter()
+        .enumerate()
+        .filter_map(|(i, step)| checked(i, step, || time_step(cb, step, &mut tick)))
+        .collect()
+}
+
+/// Runs a fresh `CountCB` through `random_steps_count(seed, steps)` and returns
+/// the result of every call; the same arguments always give the same trace.
+pub fn simulate_count(
+    seed: u64,
+    steps: usize,
+    failure_threshold: u32,
+    half_open_threshold: u32,
+) -> Vec<CircuitResult> {
+    let mut cb = CountCB::new(failure_threshold, half_open_threshold);
+    run_checked(&mut cb, &random_steps_count(seed, steps))
+}
 
-    fn generate_random_steps_time(seed: u64, count: usize) -> Vec<StepTime> {
-        let mut rng = StdRng::seed_from_u64(seed);
-        let mut steps = Vec::with_capacity(count);
-
-        for _ in 0..count {
-            let choice = rng.random_range(0..3);
-            steps.push(match choice {
-                0 => StepTime::Success,
-                1 => StepTime::Failure,
-                _ => StepTime::Tick,
-            });
+/// Runs a fresh `TimeCB` on a `LogicalClock` through `random_steps_time(seed, steps)`,
+/// each tick advancing the clock by `tick`, and returns the result of every call;
+/// the same arguments always give the same trace.
+pub fn simulate_time(
+    seed: u64,
+    steps: usize,
+    tick: Duration,
+    open_timeout: Duration,
+    half_open_probes_threshold: u32,
+    closed_failures_threshold: u32,
+) -> Vec<CircuitResult> {
+    let mut cb = TimeCB::with_clock(
+        open_timeout,
+        half_open_probes_threshold,
+        closed_failures_threshold,
+        LogicalClock::new(),
+    );
+    run_checked_timed(&mut cb, &random_steps_time(seed, steps), |cb| {
+        cb.clock().advance(tick)
+    })
+}
+
+fn call_step<B: CircuitBreaker>(cb: &mut B, step: &StepCount) -> CircuitResult {
+    let result = cb.call(|| match step {
+        StepCount::Success => Ok::<(), ()>(()),
+        StepCount::Failure => Err(()),
+    });
+    CircuitResult::from(&result)
+}
+
+fn time_step<B: CircuitBreaker>(
+    cb: &mut B,
+    step: &StepTime,
+    tick: &mut impl FnMut(&mut B),
+) -> Option<CircuitResult> {
+    let result = match step {
+        StepTime::Tick => {
+            tick(cb);
+            return None;
         }
+        StepTime::Success => cb.call(|| Ok::<(), ()>(())),
+        StepTime::Failure => cb.call(|| Err::<(), ()>(())),
+    };
+    Some(CircuitResult::from(&result))
+}
 
-        steps
+fn checked<S: Debug, T>(i: usize, step: &S, f: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(_) => panic!("breaker panicked at step {i}: {step:?}"),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn test_count_cb_random_sequence() {
-        let failure_threshold = 10;
-        let half_open_threshold = 4;
         let seed = 42;
         let count: usize = 100_000;
-        let mut cb = CountCB::new(failure_threshold, half_open_threshold);
-        let steps = generate_random_steps_count(seed, count);
-
-        for step in steps {
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                cb.call(match step {
-                    StepCount::Success => || Ok::<(), ()>(()),
-                    StepCount::Failure => || Err::<(), ()>(()),
-                })
-            }));
-
-            assert!(result.is_ok(), "Panic occurred with step: {:?}", step);
-        }
+
+        let results = simulate_count(seed, count, 10, 4);
+        assert_eq!(results.len(), count);
+        assert_eq!(results, simulate_count(seed, count, 10, 4));
+        assert_ne!(results, simulate_count(seed + 1, count, 10, 4));
     }
 
     #[test]
     fn test_time_cb_random_sequence() {
+        let tick = Duration::from_millis(1);
         let open_timeout = Duration::from_millis(5);
-        let half_open_probes_threshold = 5;
-        let closed_failures_threshold = 10;
         let seed = 42;
         let count = 100_000;
-        let start = Instant::now();
-        let clock = TestClock::new(start);
-        let mut cb = TimeCB::with_clock(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
+
+        let results = simulate_time(seed, count, tick, open_timeout, 5, 10);
+        let ticks = random_steps_time(seed, count)
+            .iter()
+            .filter(|step| **step == StepTime::Tick)
+            .count();
+        assert_eq!(results.len(), count - ticks);
+        assert_eq!(
+            results,
+            simulate_time(seed, count, tick, open_timeout, 5, 10)
+        );
+    }
+
+    #[test]
+    fn test_random_steps_are_deterministic() {
+        assert_eq!(random_steps_count(1, 100), random_steps_count(1, 100));
+        assert_eq!(random_steps_time(1, 100), random_steps_time(1, 100));
+        assert_ne!(random_steps_count(1, 100), random_steps_count(2, 100));
+    }
+
+    #[test]
+    fn test_run_simulation_reports_each_call() {
+        let mut cb = CountCB::new(1, 1);
+        let steps = [StepCount::Failure, StepCount::Success, StepCount::Success];
+        assert_eq!(
+            run_simulation(&mut cb, &steps),
+            vec![
+                CircuitResult::Failed,
+                CircuitResult::Rejected,
+                CircuitResult::Succeeded,
+            ]
         );
-        let steps = generate_random_steps_time(seed, count);
-
-        for step in steps {
-            match step {
-                StepTime::Tick => clock.tick(),
-                StepTime::Success => {
-                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                        cb.call(|| Ok::<(), ()>(()))
-                    }));
-                    assert!(result.is_ok(), "Panic occurred with step: {:?}", step);
-                }
-                StepTime::Failure => {
-                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                        cb.call(|| Err::<(), ()>(()))
-                    }));
-                    assert!(result.is_ok(), "Panic occurred with step: {:?}", step);
-                }
-            }
-        }
     }
 }
@@ -0,0 +1,116 @@
+//! Thread-safe wrapper around any circuit breaker
+
+use crate::cb::{CircuitBreaker, CircuitError, CircuitState};
+use std::sync::{Mutex, MutexGuard};
+
+/// Shares one breaker across threads. `Send + Sync` whenever the inner breaker is
+/// `Send`.
+///
+/// `call` holds the lock while the closure runs, so calls through the same
+/// `SyncCB` are serialized and a slow dependency stalls every caller. Callers that
+/// cannot afford that should lock only around `try_acquire` and the matching
+/// `record_success`/`record_failure` of the inner breaker. A closure that panics
+/// poisons the lock, and later calls panic too.
+#[derive(Debug)]
+pub struct SyncCB<B: CircuitBreaker> {
+    inner: Mutex<B>,
+}
+
+impl<B: CircuitBreaker> SyncCB<B> {
+    pub fn new(inner: B) -> Self {
+        SyncCB {
+            inner: Mutex::new(inner),
+        }
+    }
+
+    pub fn call<F, R, E>(&self, f: F) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+    {
+        self.lock().call(f)
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.lock().state()
+    }
+
+    pub fn reset(&self) {
+        self.lock().reset()
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner.into_inner().unwrap()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, B> {
+        self.inner.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count::CountCB;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_sync_cb_is_send_and_sync() {
+        assert_send_sync::<SyncCB<CountCB>>();
+    }
+
+    #[test]
+    fn test_threads_hammering_shared_breaker() {
+        let cb = Arc::new(SyncCB::new(CountCB::new(3, 2)));
+        let handles: Vec<_> = (0..8)
+            .map(|thread_id| {
+                let cb = Arc::clone(&cb);
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        let _ = cb.call(|| {
+                            if (thread_id + i) % 3 == 0 {
+                                Err(())
+                            } else {
+                                Ok(())
+                            }
+                        });
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let state = cb.state();
+        let cb = Arc::into_inner(cb).unwrap().into_inner();
+        assert_eq!(cb.state(), state);
+        assert_eq!(cb.failures_until_trip() == 0, state != CircuitState::Closed);
+        let metrics = cb.metrics();
+        assert_eq!(
+            metrics.succeeded + metrics.failed + metrics.rejected,
+            8 * 1000
+        );
+    }
+
+    #[test]
+    fn test_send_hook_runs_on_other_thread() {
+        let transitions = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let sink = Arc::clone(&transitions);
+        let cb = Arc::new(SyncCB::new(CountCB::new(1, 1).on_transition_send(
+            move |_, _| {
+                sink.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            },
+        )));
+
+        let shared = Arc::clone(&cb);
+        thread::spawn(move || shared.call(|| Err::<(), ()>(())))
+            .join()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(transitions.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
+}
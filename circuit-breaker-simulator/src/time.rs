@@ -1,41 +1,251 @@
-use crate::{CircuitBreaker, CircuitResult, CircuitState};
+use crate::cb::{EventLog, TransitionHook};
+use crate::{
+    BuildError, CircuitBreaker, CircuitError, CircuitResult, CircuitState, Event, Hooks,
+    LocalHooks, Metrics, RandomSource, SendHooks,
+};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::ops::Add;
+use std::sync::mpsc::Sender;
 use std::time::{Duration, Instant};
 
+/// A point in time as read from a `Clock`. Only needs to move forward and support
+/// the arithmetic a breaker does with its timeouts.
+pub trait Timestamp: Copy + Ord + Debug + Add<Duration, Output = Self> {
+    /// Time elapsed since `earlier`, or zero if `earlier` is later.
+    fn saturating_duration_since(&self, earlier: Self) -> Duration;
+}
+
+impl Timestamp for Instant {
+    fn saturating_duration_since(&self, earlier: Self) -> Duration {
+        Instant::saturating_duration_since(self, earlier)
+    }
+}
+
 pub trait Clock {
-    fn now(&self) -> Instant;
+    type Time: Timestamp;
+
+    fn now(&self) -> Self::Time;
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct RealClock;
 
 impl Clock for RealClock {
+    type Time = Instant;
+
     fn now(&self) -> Instant {
         Instant::now()
     }
 }
 
+/// Nanoseconds since the start of a `LogicalClock`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LogicalInstant(pub u64);
+
+impl Add<Duration> for LogicalInstant {
+    type Output = LogicalInstant;
+
+    fn add(self, duration: Duration) -> LogicalInstant {
+        let nanos = u64::try_from(duration.as_nanos()).expect("duration fits in u64 nanoseconds");
+        LogicalInstant(self.0.checked_add(nanos).expect("logical clock overflow"))
+    }
+}
+
+impl Timestamp for LogicalInstant {
+    fn saturating_duration_since(&self, earlier: Self) -> Duration {
+        Duration::from_nanos(self.0.saturating_sub(earlier.0))
+    }
+}
+
+/// A clock that only moves when told to, for deterministic simulations. Reach it
+/// through `TimeCB::clock` to advance a breaker's time.
+#[derive(Debug, Default)]
+pub struct LogicalClock {
+    now: Cell<LogicalInstant>,
+}
+
+impl LogicalClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for LogicalClock {
+    type Time = LogicalInstant;
+
+    fn now(&self) -> LogicalInstant {
+        self.now.get()
+    }
+}
+
+/// Opens a closed breaker once `slow_call_rate_percent` of the last `window_size`
+/// calls took at least `slow_call_duration`, whether or not they returned `Err`.
+/// Only a full window is judged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowCallRate {
+    pub slow_call_duration: Duration,
+    pub window_size: u32,
+    pub slow_call_rate_percent: u8,
+}
+
+/// How a `TimeCB` moves from `HalfOpen` back to `Closed` once its probes succeed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecoveryStrategy {
+    /// Closes as soon as `half_open_success_threshold` probes have succeeded.
+    #[default]
+    Immediate,
+    /// Stays `HalfOpen` for `duration` after the probes succeed, admitting each
+    /// call with a probability that grows linearly from 0 to 1 over it, then
+    /// closes. Any failure during the ramp reopens the breaker.
+    Ramp { duration: Duration },
+}
+
+/// Admission ramp of `RecoveryStrategy::Ramp`, with the source its draws come from.
+struct Ramp<K: Hooks> {
+    duration: Duration,
+    source: Box<K::Random>,
+    // The draw the next admission uses, taken ahead so `is_call_permitted` can read it.
+    next: f64,
+}
+
+impl<K: Hooks> Ramp<K> {
+    fn new(duration: Duration, mut source: Box<K::Random>) -> Self {
+        let next = draw(&mut *source);
+        Ramp {
+            duration,
+            source,
+            next,
+        }
+    }
+
+    /// Whether a call `elapsed` into the ramp is admitted, with probability equal
+    /// to the fraction of the ramp that has passed.
+    fn admits(&self, elapsed: Duration) -> bool {
+        elapsed >= self.duration || self.next < elapsed.as_secs_f64() / self.duration.as_secs_f64()
+    }
+}
+
+impl<K: Hooks> std::fmt::Debug for Ramp<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ramp")
+            .field("duration", &self.duration)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Spreads each open period by up to `±fraction` of its length, so breakers that
+/// opened together do not probe together.
+struct Jitter<K: Hooks> {
+    fraction: f64,
+    source: Box<K::Random>,
+}
+
+impl<K: Hooks> std::fmt::Debug for Jitter<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Jitter")
+            .field("fraction", &self.fraction)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Share of calls an `Open` breaker lets through as early probes, with the source
+/// its draws come from.
+struct OpenProbe<K: Hooks> {
+    fraction: f64,
+    source: Box<K::Random>,
+    // The draw the next admission uses, taken ahead so `is_call_permitted` can read it.
+    next: f64,
+}
+
+impl<K: Hooks> OpenProbe<K> {
+    fn new(fraction: f64, mut source: Box<K::Random>) -> Self {
+        let next = draw(&mut *source);
+        OpenProbe {
+            fraction,
+            source,
+            next,
+        }
+    }
+
+    fn admits(&self) -> bool {
+        self.next < self.fraction
+    }
+}
+
+fn draw<R: ?Sized + RandomSource>(source: &mut R) -> f64 {
+    let random = source.next_f64();
+    assert!((0.0..1.0).contains(&random));
+    random
+}
+
+impl<K: Hooks> std::fmt::Debug for OpenProbe<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenProbe")
+            .field("fraction", &self.fraction)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug)]
-pub struct TimeCB<C: Clock = RealClock> {
+pub struct TimeCB<C: Clock = RealClock, K: Hooks = SendHooks> {
     clock: C,
     state: CircuitState,
     open_timeout: Duration,
-    open_at: Option<Instant>,
-    closed_failures: u8,
-    closed_failures_threshold: u8,
-    half_open_probes: u8,
-    half_open_probes_threshold: u8,
+    // The open duration currently in force; grows past `open_timeout` with backoff.
+    open_for: Duration,
+    open_timeout_max: Duration,
+    open_timeout_multiplier: u32,
+    // `open_for` with jitter applied, fixed for the current open period.
+    open_period: Duration,
+    jitter: Option<Jitter<K>>,
+    open_probe: Option<OpenProbe<K>>,
+    // Early probes admitted while `Open`; scored without leaving `Open`.
+    open_probes_in_flight: u32,
+    open_probe_successes: u32,
+    max_open_lifetime: Option<Duration>,
+    half_open_timeout: Option<Duration>,
+    ramp: Option<Ramp<K>>,
+    // When the recovery ramp began; `Some` only while `HalfOpen`.
+    ramp_started_at: Option<C::Time>,
+    open_at: Option<C::Time>,
+    closed_failures: u32,
+    closed_failures_threshold: u32,
+    half_open_probes: u32,
+    half_open_probes_threshold: u32,
+    heal_half_open_probes: bool,
+    half_open_in_flight: u32,
+    half_open_max_concurrent: u32,
+    half_open_successes: u32,
+    half_open_success_threshold: u32,
+    transition_sender: Option<Sender<(CircuitState, CircuitState)>>,
+    state_entered_at: C::Time,
+    on_transition: Option<TransitionHook<K>>,
+    version: u64,
+    metrics: Metrics,
+    event_log: Option<EventLog<C::Time>>,
+    disabled: bool,
+    slow_call_rate: Option<SlowCallRate>,
+    slow_call_threshold: Option<Duration>,
+    // true for a slow call, oldest first.
+    slow_calls: VecDeque<bool>,
+    slow_calls_count: u32,
+    #[cfg(feature = "log")]
+    name: Option<String>,
 }
 
 impl TimeCB<RealClock> {
+    /// Panics on a setting `try_new` would reject.
     pub fn new(
         open_timeout: Duration,
-        half_open_probes_threshold: u8,
-        closed_failures_threshold: u8,
+        half_open_probes_threshold: u32,
+        closed_failures_threshold: u32,
     ) -> Self {
-        assert!(open_timeout > Duration::from_millis(0));
-        assert!(half_open_probes_threshold > 0);
-        assert!(closed_failures_threshold > 0);
-
         Self::with_clock(
             open_timeout,
             half_open_probes_threshold,
@@ -43,420 +253,2570 @@ impl TimeCB<RealClock> {
             RealClock,
         )
     }
+
+    pub fn try_new(
+        open_timeout: Duration,
+        half_open_probes_threshold: u32,
+        closed_failures_threshold: u32,
+    ) -> Result<Self, BuildError> {
+        Self::try_with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            RealClock,
+        )
+    }
 }
 
 impl<C: Clock> TimeCB<C> {
+    /// Panics on a setting `try_with_clock` would reject.
     pub fn with_clock(
         open_timeout: Duration,
-        half_open_probes_threshold: u8,
-        closed_failures_threshold: u8,
+        half_open_probes_threshold: u32,
+        closed_failures_threshold: u32,
         clock: C,
     ) -> Self {
-        assert!(open_timeout > Duration::from_millis(0));
-        assert!(half_open_probes_threshold > 0);
-        assert!(closed_failures_threshold > 0);
+        Self::try_with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock,
+        )
+        .unwrap_or_else(|error| panic!("{error}"))
+    }
 
-        TimeCB {
+    pub fn try_with_clock(
+        open_timeout: Duration,
+        half_open_probes_threshold: u32,
+        closed_failures_threshold: u32,
+        clock: C,
+    ) -> Result<Self, BuildError> {
+        if open_timeout == Duration::ZERO {
+            return Err(BuildError::ZeroOpenTimeout);
+        }
+        if closed_failures_threshold == 0 {
+            return Err(BuildError::ZeroFailureThreshold);
+        }
+        if half_open_probes_threshold == 0 {
+            return Err(BuildError::ZeroHalfOpenThreshold);
+        }
+
+        let now = clock.now();
+        Ok(TimeCB {
             clock,
+            state_entered_at: now,
             state: CircuitState::Closed,
             open_at: None,
             half_open_probes: 0,
             closed_failures: 0,
             closed_failures_threshold,
             open_timeout,
+            open_for: open_timeout,
+            open_timeout_max: open_timeout,
+            open_timeout_multiplier: 1,
+            open_period: open_timeout,
+            jitter: None,
+            open_probe: None,
+            open_probes_in_flight: 0,
+            open_probe_successes: 0,
+            max_open_lifetime: None,
+            half_open_timeout: None,
+            ramp: None,
+            ramp_started_at: None,
             half_open_probes_threshold,
+            heal_half_open_probes: false,
+            half_open_in_flight: 0,
+            half_open_max_concurrent: u32::MAX,
+            half_open_successes: 0,
+            half_open_success_threshold: 1,
+            transition_sender: None,
+            on_transition: None,
+            version: 0,
+            metrics: Metrics::default(),
+            event_log: None,
+            disabled: false,
+            slow_call_rate: None,
+            slow_call_threshold: None,
+            slow_calls: VecDeque::new(),
+            slow_calls_count: 0,
+            #[cfg(feature = "log")]
+            name: None,
+        })
+    }
+
+    /// Lets `on_transition` take a callback, and the jitter, open-probe and ramp
+    /// setters a random source, that is not `Send`, which makes the breaker `!Send`.
+    pub fn local(self) -> TimeCB<C, LocalHooks> {
+        TimeCB {
+            clock: self.clock,
+            state: self.state,
+            open_timeout: self.open_timeout,
+            open_for: self.open_for,
+            open_timeout_max: self.open_timeout_max,
+            open_timeout_multiplier: self.open_timeout_multiplier,
+            open_period: self.open_period,
+            jitter: self.jitter.map(|Jitter { fraction, source }| Jitter {
+                fraction,
+                source: source as Box<dyn RandomSource>,
+            }),
+            open_probe: self.open_probe.map(
+                |OpenProbe {
+                     fraction,
+                     source,
+                     next,
+                 }| OpenProbe {
+                    fraction,
+                    source: source as Box<dyn RandomSource>,
+                    next,
+                },
+            ),
+            open_probes_in_flight: self.open_probes_in_flight,
+            open_probe_successes: self.open_probe_successes,
+            max_open_lifetime: self.max_open_lifetime,
+            half_open_timeout: self.half_open_timeout,
+            ramp: self.ramp.map(
+                |Ramp {
+                     duration,
+                     source,
+                     next,
+                 }| Ramp {
+                    duration,
+                    source: source as Box<dyn RandomSource>,
+                    next,
+                },
+            ),
+            ramp_started_at: self.ramp_started_at,
+            open_at: self.open_at,
+            closed_failures: self.closed_failures,
+            closed_failures_threshold: self.closed_failures_threshold,
+            half_open_probes: self.half_open_probes,
+            half_open_probes_threshold: self.half_open_probes_threshold,
+            heal_half_open_probes: self.heal_half_open_probes,
+            half_open_in_flight: self.half_open_in_flight,
+            half_open_max_concurrent: self.half_open_max_concurrent,
+            half_open_successes: self.half_open_successes,
+            half_open_success_threshold: self.half_open_success_threshold,
+            transition_sender: self.transition_sender,
+            state_entered_at: self.state_entered_at,
+            on_transition: self.on_transition.map(TransitionHook::local),
+            version: self.version,
+            metrics: self.metrics,
+            event_log: self.event_log,
+            disabled: self.disabled,
+            slow_call_rate: self.slow_call_rate,
+            slow_call_threshold: self.slow_call_threshold,
+            slow_calls: self.slow_calls,
+            slow_calls_count: self.slow_calls_count,
+            #[cfg(feature = "log")]
+            name: self.name,
         }
     }
+
+    /// Calls `f(from, to)` once for every state transition. `f` need not be
+    /// `Send`, so the breaker becomes `!Send`; `on_transition_send` keeps it `Send`.
+    pub fn on_transition(
+        self,
+        f: impl FnMut(CircuitState, CircuitState) + 'static,
+    ) -> TimeCB<C, LocalHooks> {
+        self.local().on_transition(f)
+    }
+
+    /// Like `on_transition`, for a `Send` callback, so the breaker stays `Send`.
+    pub fn on_transition_send(
+        mut self,
+        f: impl FnMut(CircuitState, CircuitState) + Send + 'static,
+    ) -> Self {
+        self.on_transition = Some(TransitionHook::<SendHooks>::new(f));
+        self
+    }
 }
 
-impl<C: Clock> CircuitBreaker for TimeCB<C> {
-    fn call<F, R>(&mut self, f: F) -> CircuitResult
-    where
-        F: FnOnce() -> Result<R, ()>,
-    {
+impl<C: Clock> TimeCB<C, LocalHooks> {
+    /// Calls `f(from, to)` once for every state transition.
+    pub fn on_transition(mut self, f: impl FnMut(CircuitState, CircuitState) + 'static) -> Self {
+        self.on_transition = Some(TransitionHook::<LocalHooks>::new(f));
+        self
+    }
+}
+
+impl<C: Clock, K: Hooks> TimeCB<C, K> {
+    /// Grows the open duration by `multiplier` each time a half-open failure reopens
+    /// the breaker, up to `open_timeout_max`. It drops back to `open_timeout` once
+    /// the breaker closes.
+    pub fn open_timeout_backoff(mut self, multiplier: u32, open_timeout_max: Duration) -> Self {
+        assert!(multiplier > 0);
+        assert!(open_timeout_max >= self.open_timeout);
+
+        self.open_timeout_multiplier = multiplier;
+        self.open_timeout_max = open_timeout_max;
+        self
+    }
+
+    /// Randomizes each open period by up to `±fraction` of the effective open timeout,
+    /// drawing from `source`.
+    pub fn open_timeout_jitter(mut self, fraction: f64, source: Box<K::Random>) -> Self {
+        assert!(fraction > 0.0);
+        assert!(fraction < 1.0);
+
+        self.jitter = Some(Jitter { fraction, source });
+        self
+    }
+
+    /// Lets about `fraction` of the calls an `Open` breaker would reject through
+    /// before its open period ends, drawing from `source`, so recovery is noticed
+    /// without waiting out the timeout. The breaker stays `Open` while they run:
+    /// `half_open_success_threshold` successes in a row recover it, and a failure
+    /// only restarts that count, without backoff. None are admitted by default.
+    pub fn open_probe_fraction(mut self, fraction: f64, source: Box<K::Random>) -> Self {
+        assert!(fraction > 0.0);
+        assert!(fraction < 1.0);
+
+        self.open_probe = Some(OpenProbe::new(fraction, source));
+        self
+    }
+
+    /// Caps how long the breaker stays `Open`, whatever backoff and jitter say.
+    /// Combined with `poll`, an idle breaker still moves to `HalfOpen` on time.
+    pub fn max_open_lifetime(mut self, max_open_lifetime: Duration) -> Self {
+        assert!(max_open_lifetime > Duration::ZERO);
+
+        self.max_open_lifetime = Some(max_open_lifetime);
+        self
+    }
+
+    /// Reopens a breaker that has been `HalfOpen` for `half_open_timeout` without
+    /// resolving: the next call after that is rejected and the breaker opens again,
+    /// backing off as if a probe had failed.
+    pub fn half_open_timeout(mut self, half_open_timeout: Duration) -> Self {
+        assert!(half_open_timeout > Duration::ZERO);
+
+        self.half_open_timeout = Some(half_open_timeout);
+        self
+    }
+
+    /// Chooses how the breaker recovers once its half-open probes succeed. `source`
+    /// supplies the admission draws of `RecoveryStrategy::Ramp`.
+    pub fn recovery_strategy(mut self, strategy: RecoveryStrategy, source: Box<K::Random>) -> Self {
+        self.ramp = match strategy {
+            RecoveryStrategy::Immediate => None,
+            RecoveryStrategy::Ramp { duration } => {
+                assert!(duration > Duration::ZERO);
+                Some(Ramp::new(duration, source))
+            }
+        };
+        self
+    }
+
+    /// Moves an `Open` breaker to `HalfOpen` once its open period has passed, without
+    /// waiting for a call. `state` alone never transitions, so a monitor that wants
+    /// an idle breaker to recover should poll it.
+    pub fn poll(&mut self) {
+        if self.disabled {
+            return;
+        }
+        let now = self.clock.now();
+        if self.state == CircuitState::Open && self.open_until() <= now {
+            self.half_open(now);
+        }
+    }
+
+    /// How long until an `Open` breaker admits a probe, counting backoff, jitter and
+    /// `max_open_lifetime`. `Some(Duration::ZERO)` once that time has come but no call
+    /// or `poll` has moved it on; `None` in any other state.
+    pub fn time_until_half_open(&self) -> Option<Duration> {
+        match self.state {
+            CircuitState::Open => Some(
+                self.open_until()
+                    .saturating_duration_since(self.clock.now()),
+            ),
+            CircuitState::Closed | CircuitState::HalfOpen => None,
+        }
+    }
+
+    fn open_until(&self) -> C::Time {
+        assert!(self.open_at.is_some());
+
+        let open_period = match self.max_open_lifetime {
+            Some(max_open_lifetime) => self.open_period.min(max_open_lifetime),
+            None => self.open_period,
+        };
+        self.open_at.unwrap() + open_period
+    }
+
+    /// The base open duration, before backoff and jitter.
+    pub fn open_timeout(&self) -> Duration {
+        self.open_timeout
+    }
+
+    /// Changes the base open duration from the next open period on; a breaker that
+    /// is already `Open` keeps the period it opened with. A backed-off duration is
+    /// kept, raised to `open_timeout` if needed.
+    pub fn set_open_timeout(&mut self, open_timeout: Duration) {
+        assert!(open_timeout > Duration::ZERO);
+        assert!(self.open_timeout <= self.open_for);
+
+        self.open_for = if self.open_for == self.open_timeout {
+            open_timeout
+        } else {
+            self.open_for.max(open_timeout)
+        };
+        self.open_timeout_max = self.open_timeout_max.max(open_timeout);
+        self.open_timeout = open_timeout;
+        assert!(self.open_for <= self.open_timeout_max);
+    }
+
+    /// How long the breaker stays open the next time it opens, or is staying open now,
+    /// before jitter.
+    pub fn effective_open_timeout(&self) -> Duration {
+        assert!(self.open_timeout <= self.open_for);
+        assert!(self.open_for <= self.open_timeout_max);
+
+        self.open_for
+    }
+
+    /// How long the breaker has been in its current state: staleness while `Open`,
+    /// uptime since the last recovery while `Closed`.
+    pub fn time_in_state(&self) -> Duration {
+        self.clock
+            .now()
+            .saturating_duration_since(self.state_entered_at)
+    }
+
+    /// Bypasses the breaker: every call runs and nothing is recorded, not even
+    /// metrics, until `enable`. `state` keeps reporting the state it was left in.
+    pub fn disable(&mut self) {
+        self.disabled = true;
+    }
+
+    /// Turns a disabled breaker back on, starting from `Closed` with cleared counters.
+    pub fn enable(&mut self) {
+        if self.disabled {
+            self.disabled = false;
+            self.reset();
+        }
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        self.metrics
+    }
+
+    /// Events kept by `event_log`, oldest first; empty when it is not enabled.
+    pub fn events(&self) -> impl Iterator<Item = &Event<C::Time>> {
+        self.event_log.iter().flat_map(EventLog::iter)
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Consecutive failures counted while `Closed`; equals the threshold once open.
+    pub fn failure_count(&self) -> u32 {
+        self.closed_failures
+    }
+
+    /// Failed probes counted while `HalfOpen`.
+    pub fn half_open_attempts(&self) -> u32 {
+        self.half_open_probes
+    }
+
+    /// Failures left before the breaker opens; 0 once it is no longer `Closed`.
+    pub fn failures_until_trip(&self) -> u32 {
         match self.state {
             CircuitState::Closed => {
                 assert!(self.closed_failures < self.closed_failures_threshold);
-                assert!(self.half_open_probes == 0);
-                assert!(self.open_at.is_none());
+                self.closed_failures_threshold - self.closed_failures
+            }
+            CircuitState::Open | CircuitState::HalfOpen => 0,
+        }
+    }
+
+    /// Keeps the last `capacity` calls for `events`, each stamped with the clock
+    /// reading the breaker took it at.
+    pub fn event_log(mut self, capacity: usize) -> Self {
+        self.event_log = Some(EventLog::new(capacity));
+        self
+    }
+
+    /// Sends every state transition as `(from, to)` on `tx`.
+    pub fn transition_sender(mut self, tx: Sender<(CircuitState, CircuitState)>) -> Self {
+        self.transition_sender = Some(tx);
+        self
+    }
+
+    /// Caps how many probes may be in flight at once while `HalfOpen`; further calls
+    /// are rejected until a probe resolves. Unlimited by default.
+    pub fn half_open_max_concurrent(mut self, half_open_max_concurrent: u32) -> Self {
+        assert!(half_open_max_concurrent > 0);
+
+        self.half_open_max_concurrent = half_open_max_concurrent;
+        self
+    }
+
+    /// Consecutive successful probes needed to close from `HalfOpen`; 1 by default.
+    /// A failed probe starts the count again.
+    pub fn half_open_success_threshold(mut self, half_open_success_threshold: u32) -> Self {
+        assert!(half_open_success_threshold > 0);
+
+        self.half_open_success_threshold = half_open_success_threshold;
+        self
+    }
+
+    /// Lets each successful probe take back one failed probe, so intermittent
+    /// failures only reopen the breaker once they outnumber the successes by
+    /// `half_open_probes_threshold`. By default every failed probe counts until the
+    /// breaker closes or reopens.
+    pub fn heal_half_open_probes(mut self) -> Self {
+        self.heal_half_open_probes = true;
+        self
+    }
+
+    /// Opens the breaker on a high rate of slow calls, measured with the breaker's clock.
+    pub fn slow_call_rate(mut self, slow_call_rate: SlowCallRate) -> Self {
+        assert!(slow_call_rate.slow_call_duration > Duration::ZERO);
+        assert!(slow_call_rate.window_size > 0);
+        assert!(slow_call_rate.slow_call_rate_percent > 0);
+        assert!(slow_call_rate.slow_call_rate_percent <= 100);
+
+        self.slow_calls = VecDeque::with_capacity(slow_call_rate.window_size as usize);
+        self.slow_call_rate = Some(slow_call_rate);
+        self
+    }
+
+    /// Counts a call that takes longer than `slow_call_threshold` as a failure even
+    /// when it returns `Ok`, measured with the breaker's clock; such a call returns
+    /// `CircuitError::TimedOut`. `None` disables it.
+    pub fn slow_call_threshold(mut self, slow_call_threshold: Option<Duration>) -> Self {
+        assert!(slow_call_threshold.is_none_or(|threshold| threshold > Duration::ZERO));
+
+        self.slow_call_threshold = slow_call_threshold;
+        self
+    }
+
+    /// Names the breaker in log records.
+    #[cfg(feature = "log")]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    fn transition(&mut self, to: CircuitState, now: C::Time) {
+        assert!(self.state != to);
+
+        let from = self.state;
+        self.state = to;
+        self.state_entered_at = now;
+        self.version += 1;
+        if to == CircuitState::Open {
+            self.metrics.opens += 1;
+        }
+        if let Some(tx) = &self.transition_sender {
+            // A dropped receiver means nobody is listening, not that the breaker failed.
+            let _ = tx.send((from, to));
+        }
+        if let Some(TransitionHook(on_transition)) = &mut self.on_transition {
+            on_transition(from, to);
+        }
+        #[cfg(feature = "log")]
+        crate::cb::log_transition(self.name.as_deref(), from, to);
+    }
+
+    /// Like `call`, but takes every timing decision against `now` instead of reading
+    /// the clock, for callers such as event loops that already own time. `now` must
+    /// not go backwards between calls.
+    ///
+    /// Slow-call detection still reads the clock once the closure returns, so `now`
+    /// should come from the same clock when either slow-call option is enabled.
+    pub fn call_at<F, R, E>(&mut self, now: C::Time, f: F) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+    {
+        self.call_inner(now, self.slow_call_threshold, f, |_| true)
+    }
+
+    /// Like `call`, but counts the call as a failure when the clock shows it ran past
+    /// `deadline`. The closure is never interrupted: a hung dependency is only
+    /// classified once it returns, and an `Ok` past the deadline is returned as
+    /// `CircuitError::TimedOut`.
+    pub fn call_with_deadline<F, R, E>(
+        &mut self,
+        deadline: Duration,
+        f: F,
+    ) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+    {
+        let slow_call_threshold = match self.slow_call_threshold {
+            Some(slow_call_threshold) => slow_call_threshold.min(deadline),
+            None => deadline,
+        };
+        let now = self.clock.now();
+        self.call_inner(now, Some(slow_call_threshold), f, |_| true)
+    }
+
+    fn call_inner<F, R, E, P>(
+        &mut self,
+        now: C::Time,
+        slow_call_threshold: Option<Duration>,
+        f: F,
+        should_count_failure: P,
+    ) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+        P: FnOnce(&E) -> bool,
+    {
+        if self.disabled {
+            return f().map_err(CircuitError::Failed);
+        }
+        if !self.acquire_at(now) {
+            self.metrics.rejected += 1;
+            self.record_event(CircuitResult::Rejected, now);
+            return Err(CircuitError::Rejected);
+        }
 
-                let result = f();
-                match result {
-                    Ok(_) => {
-                        self.closed_failures = 0;
-                        CircuitResult::Succeeded
+        let result = f();
+        if let Err(error) = &result {
+            if !should_count_failure(error) {
+                match self.state {
+                    CircuitState::Closed => {}
+                    CircuitState::Open => {
+                        self.open_probes_in_flight = self.open_probes_in_flight.saturating_sub(1);
                     }
-                    Err(_) => {
-                        self.closed_failures += 1;
-                        if self.closed_failures == self.closed_failures_threshold {
-                            self.state = CircuitState::Open;
-                            self.open_at = Some(self.clock.now());
-                        }
-                        CircuitResult::Failed
+                    CircuitState::HalfOpen => {
+                        self.half_open_in_flight = self.half_open_in_flight.saturating_sub(1);
                     }
                 }
+                return result.map_err(CircuitError::Failed);
+            }
+        }
+        let elapsed = if self.slow_call_rate.is_some() || slow_call_threshold.is_some() {
+            Some(self.clock.now().saturating_duration_since(now))
+        } else {
+            None
+        };
+        let slow = match (&self.slow_call_rate, elapsed) {
+            (Some(slow_call_rate), Some(elapsed)) if self.state == CircuitState::Closed => {
+                elapsed >= slow_call_rate.slow_call_duration
+            }
+            _ => false,
+        };
+        let slow_failure = match (slow_call_threshold, elapsed) {
+            (Some(slow_call_threshold), Some(elapsed)) => elapsed > slow_call_threshold,
+            _ => false,
+        };
+
+        let (result, circuit_result) = match result {
+            Ok(_) if slow_failure => {
+                self.on_failure(now);
+                (Err(CircuitError::TimedOut), CircuitResult::TimedOut)
+            }
+            Ok(value) => {
+                self.on_success(now);
+                (Ok(value), CircuitResult::Succeeded)
+            }
+            Err(error) => {
+                self.on_failure(now);
+                (Err(CircuitError::Failed(error)), CircuitResult::Failed)
+            }
+        };
+
+        if self.state == CircuitState::Closed && self.record_slow_call(slow) {
+            self.open(now);
+        }
+        self.record_event(circuit_result, now);
+        result
+    }
+
+    fn acquire_at(&mut self, now: C::Time) -> bool {
+        match self.state {
+            CircuitState::Closed => {
+                assert!(self.closed_failures < self.closed_failures_threshold);
+                assert!(self.half_open_probes == 0);
+                assert!(self.half_open_in_flight == 0);
+                assert!(self.half_open_successes == 0);
+                assert!(self.open_at.is_none());
+                true
             }
             CircuitState::Open => {
                 assert!(self.closed_failures == self.closed_failures_threshold);
                 assert!(self.half_open_probes == 0);
+                assert!(self.half_open_in_flight == 0);
+                assert!(self.half_open_successes == 0);
+                assert!(self.open_probe_successes < self.half_open_success_threshold);
                 assert!(self.open_at.is_some());
 
-                if self.open_at.unwrap() + self.open_timeout <= self.clock.now() {
-                    self.state = CircuitState::HalfOpen;
-                    self.half_open_probes = 0;
-
-                    let result = f();
-                    match result {
-                        Ok(_) => {
-                            self.state = CircuitState::Closed;
-                            self.closed_failures = 0;
-                            self.open_at = None;
-                            self.half_open_probes = 0;
-                            CircuitResult::Succeeded
-                        }
-                        Err(_) => {
-                            self.half_open_probes += 1;
-                            if self.half_open_probes == self.half_open_probes_threshold {
-                                self.state = CircuitState::Open;
-                                self.half_open_probes = 0;
-                                self.open_at = Some(self.clock.now());
-                            }
-                            CircuitResult::Failed
-                        }
+                if self.open_until() > now {
+                    if !self.admit_open_probe() {
+                        return false;
                     }
-                } else {
-                    CircuitResult::Rejected
+                    self.open_probes_in_flight += 1;
+                    return true;
                 }
+                self.half_open(now);
+                self.half_open_in_flight = 1;
+                true
             }
+            CircuitState::HalfOpen if self.ramp_started_at.is_some() => self.acquire_ramping(now),
             CircuitState::HalfOpen => {
                 assert!(self.closed_failures == self.closed_failures_threshold);
                 assert!(self.half_open_probes < self.half_open_probes_threshold);
+                assert!(self.half_open_in_flight <= self.half_open_max_concurrent);
+                assert!(self.half_open_successes < self.half_open_success_threshold);
                 assert!(self.open_at.is_some());
-                assert!(self.open_at.unwrap() + self.open_timeout <= self.clock.now());
-
-                let result = f();
-                match result {
-                    Ok(_) => {
-                        self.state = CircuitState::Closed;
-                        self.closed_failures = 0;
-                        self.open_at = None;
-                        self.half_open_probes = 0;
-                        CircuitResult::Succeeded
-                    }
-                    Err(_) => {
-                        self.half_open_probes += 1;
-                        if self.half_open_probes == self.half_open_probes_threshold {
-                            self.state = CircuitState::Open;
-                            self.half_open_probes = 0;
-                            self.open_at = Some(self.clock.now());
-                        }
-                        CircuitResult::Failed
+                assert!(self.open_until() <= now);
+                assert!(self.state_entered_at <= now);
+
+                if let Some(half_open_timeout) = self.half_open_timeout {
+                    if self.state_entered_at + half_open_timeout <= now {
+                        self.reopen(now);
+                        return false;
                     }
                 }
+                if self.half_open_in_flight == self.half_open_max_concurrent {
+                    return false;
+                }
+                self.half_open_in_flight += 1;
+                true
             }
         }
     }
 
-    fn state(&self) -> CircuitState {
-        self.state
+    /// Whether an `Open` breaker lets this call through as an early probe.
+    fn admit_open_probe(&mut self) -> bool {
+        assert!(self.state == CircuitState::Open);
+
+        let Some(open_probe) = &mut self.open_probe else {
+            return false;
+        };
+        let admitted = open_probe.admits();
+        open_probe.next = draw(&mut *open_probe.source);
+        admitted
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Admits a call during the recovery ramp with probability equal to the
+    /// fraction of the ramp that has passed, closing once all of it has.
+    fn acquire_ramping(&mut self, now: C::Time) -> bool {
+        assert!(self.state == CircuitState::HalfOpen);
+        assert!(self.ramp.is_some());
+
+        let started_at = self.ramp_started_at.unwrap();
+        let ramp = self.ramp.as_mut().unwrap();
+        let elapsed = now.saturating_duration_since(started_at);
+        if elapsed >= ramp.duration {
+            self.close(now);
+            return true;
+        }
+
+        let admitted = ramp.admits(elapsed);
+        ramp.next = draw(&mut *ramp.source);
+        if !admitted {
+            return false;
+        }
+        self.half_open_in_flight += 1;
+        true
+    }
+
+    fn on_success(&mut self, now: C::Time) {
+        self.metrics.succeeded += 1;
+        match self.state {
+            CircuitState::Closed => self.closed_failures = 0,
+            CircuitState::HalfOpen if self.ramp_started_at.is_some() => {
+                self.half_open_in_flight = self.half_open_in_flight.saturating_sub(1);
+            }
+            CircuitState::HalfOpen => {
+                self.half_open_successes += 1;
+                self.half_open_in_flight = self.half_open_in_flight.saturating_sub(1);
+                if self.heal_half_open_probes {
+                    self.half_open_probes = self.half_open_probes.saturating_sub(1);
+                }
+                if self.half_open_successes == self.half_open_success_threshold {
+                    self.recover(now);
+                }
+            }
+            CircuitState::Open => {
+                self.open_probes_in_flight = self.open_probes_in_flight.saturating_sub(1);
+                self.open_probe_successes += 1;
+                if self.open_probe_successes == self.half_open_success_threshold {
+                    self.half_open(now);
+                    self.recover(now);
+                }
+            }
+        }
+    }
+
+    fn on_failure(&mut self, now: C::Time) {
+        self.metrics.failed += 1;
+        match self.state {
+            CircuitState::Closed => {
+                self.closed_failures += 1;
+                if self.closed_failures == self.closed_failures_threshold {
+                    self.open(now);
+                }
+            }
+            CircuitState::HalfOpen if self.ramp_started_at.is_some() => self.reopen(now),
+            CircuitState::HalfOpen => {
+                self.half_open_probes += 1;
+                self.half_open_successes = 0;
+                self.half_open_in_flight = self.half_open_in_flight.saturating_sub(1);
+                if self.half_open_probes == self.half_open_probes_threshold {
+                    self.reopen(now);
+                }
+            }
+            CircuitState::Open => {
+                self.open_probes_in_flight = self.open_probes_in_flight.saturating_sub(1);
+                self.open_probe_successes = 0;
+            }
+        }
+    }
+
+    fn record_event(&mut self, result: CircuitResult, now: C::Time) {
+        if let Some(event_log) = &mut self.event_log {
+            event_log.push(Event {
+                at: now,
+                result,
+                state: self.state,
+            });
+        }
+    }
+
+    /// Records whether the last closed call was slow and reports whether the slow
+    /// call rate has been reached over a full window.
+    fn record_slow_call(&mut self, slow: bool) -> bool {
+        let Some(slow_call_rate) = &self.slow_call_rate else {
+            return false;
+        };
+        let window_size = slow_call_rate.window_size as usize;
+        assert!(self.slow_calls.len() <= window_size);
+
+        if self.slow_calls.len() == window_size && self.slow_calls.pop_front().unwrap() {
+            self.slow_calls_count -= 1;
+        }
+        self.slow_calls.push_back(slow);
+        if slow {
+            self.slow_calls_count += 1;
+        }
+
+        self.slow_calls.len() == window_size
+            && self.slow_calls_count as u64 * 100
+                >= slow_call_rate.slow_call_rate_percent as u64 * window_size as u64
+    }
+
+    fn open(&mut self, now: C::Time) {
+        self.transition(CircuitState::Open, now);
+        self.closed_failures = self.closed_failures_threshold;
+        self.half_open_probes = 0;
+        self.half_open_in_flight = 0;
+        self.half_open_successes = 0;
+        self.ramp_started_at = None;
+        self.open_probes_in_flight = 0;
+        self.open_probe_successes = 0;
+        self.open_at = Some(now);
+        self.open_period = match &mut self.jitter {
+            Some(jitter) => {
+                let random = draw(&mut *jitter.source);
+                self.open_for
+                    .mul_f64(1.0 + jitter.fraction * (2.0 * random - 1.0))
+            }
+            None => self.open_for,
+        };
+        self.slow_calls.clear();
+        self.slow_calls_count = 0;
+    }
+
+    fn half_open(&mut self, now: C::Time) {
+        self.transition(CircuitState::HalfOpen, now);
+        self.open_probes_in_flight = 0;
+        self.open_probe_successes = 0;
+    }
+
+    /// Closes a `HalfOpen` breaker whose probes succeeded, or starts the recovery
+    /// ramp when one is configured.
+    fn recover(&mut self, now: C::Time) {
+        assert!(self.state == CircuitState::HalfOpen);
+
+        if self.ramp.is_some() {
+            self.ramp_started_at = Some(now);
+        } else {
+            self.close(now);
+        }
+    }
+
+    /// Opens again after a failed recovery, backing off the open duration.
+    fn reopen(&mut self, now: C::Time) {
+        assert!(self.state == CircuitState::HalfOpen);
+
+        self.open_for = self
+            .open_for
+            .checked_mul(self.open_timeout_multiplier)
+            .map_or(self.open_timeout_max, |open_for| {
+                open_for.min(self.open_timeout_max)
+            });
+        self.open(now);
+    }
+
+    fn close(&mut self, now: C::Time) {
+        self.transition(CircuitState::Closed, now);
+        self.open_for = self.open_timeout;
+        self.closed_failures = 0;
+        self.half_open_probes = 0;
+        self.half_open_in_flight = 0;
+        self.half_open_successes = 0;
+        self.ramp_started_at = None;
+        self.open_at = None;
+    }
+}
+
+impl<C: Clock, K: Hooks> CircuitBreaker for TimeCB<C, K> {
+    fn call_classified<F, R, E, P>(
+        &mut self,
+        f: F,
+        should_count_failure: P,
+    ) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+        P: FnOnce(&E) -> bool,
+    {
+        // A single reading keeps every comparison and timestamp in this call consistent.
+        let now = self.clock.now();
+        self.call_inner(now, self.slow_call_threshold, f, should_count_failure)
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        if self.disabled {
+            return true;
+        }
+        let now = self.clock.now();
+        let acquired = self.acquire_at(now);
+        if !acquired {
+            self.metrics.rejected += 1;
+            self.record_event(CircuitResult::Rejected, now);
+        }
+        acquired
+    }
+
+    fn record_success(&mut self) {
+        let stale = self.state == CircuitState::Open && self.open_probes_in_flight == 0;
+        if self.disabled || stale {
+            return;
+        }
+        let now = self.clock.now();
+        self.on_success(now);
+        self.record_event(CircuitResult::Succeeded, now);
+    }
+
+    fn record_failure(&mut self) {
+        let stale = self.state == CircuitState::Open && self.open_probes_in_flight == 0;
+        if self.disabled || stale {
+            return;
+        }
+        let now = self.clock.now();
+        self.on_failure(now);
+        self.record_event(CircuitResult::Failed, now);
+    }
+
+    fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Mirrors the decision `try_acquire` would make now, reading the draws the
+    /// open-probe and ramp admissions have taken ahead.
+    fn is_call_permitted(&self) -> bool {
+        if self.disabled {
+            return true;
+        }
+        let now = self.clock.now();
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::Open if self.open_until() > now => self
+                .open_probe
+                .as_ref()
+                .is_some_and(|open_probe| open_probe.admits()),
+            CircuitState::Open => true,
+            CircuitState::HalfOpen if self.ramp_started_at.is_some() => {
+                let started_at = self.ramp_started_at.unwrap();
+                let ramp = self.ramp.as_ref().unwrap();
+                ramp.admits(now.saturating_duration_since(started_at))
+            }
+            CircuitState::HalfOpen => {
+                let timed_out = self.half_open_timeout.is_some_and(|half_open_timeout| {
+                    self.state_entered_at + half_open_timeout <= now
+                });
+                !timed_out && self.half_open_in_flight < self.half_open_max_concurrent
+            }
+        }
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn reset(&mut self) {
+        if self.state != CircuitState::Closed {
+            let now = self.clock.now();
+            self.transition(CircuitState::Closed, now);
+        }
+        self.open_for = self.open_timeout;
+        self.closed_failures = 0;
+        self.half_open_probes = 0;
+        self.half_open_in_flight = 0;
+        self.half_open_successes = 0;
+        self.ramp_started_at = None;
+        self.open_probes_in_flight = 0;
+        self.open_probe_successes = 0;
+        self.open_at = None;
+        self.slow_calls.clear();
+        self.slow_calls_count = 0;
+    }
+
+    fn trip(&mut self) {
+        if self.state == CircuitState::Open {
+            self.open_at = Some(self.clock.now());
+        } else {
+            let now = self.clock.now();
+            self.open(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    #[derive(Debug, Clone)]
+    struct TestClock {
+        now: Rc<Cell<Instant>>,
+    }
+
+    impl Clock for TestClock {
+        type Time = Instant;
+
+        fn now(&self) -> Instant {
+            self.now.get()
+        }
+    }
+
+    impl TestClock {
+        const TICK: Duration = Duration::from_millis(1);
+
+        fn new(start: Instant) -> Self {
+            Self {
+                now: Rc::new(Cell::new(start)),
+            }
+        }
+
+        fn tick(&self) {
+            self.now.set(self.now.get() + Self::TICK);
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct CountingClock {
+        now: Rc<Cell<Instant>>,
+        reads: Rc<Cell<usize>>,
+    }
+
+    impl Clock for CountingClock {
+        type Time = Instant;
+
+        fn now(&self) -> Instant {
+            self.reads.set(self.reads.get() + 1);
+            self.now.get()
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_open_timeout_panics() {
+        let open_timeout = Duration::from_millis(0);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 2;
+        let _ = TimeCB::new(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_half_open_probes_threshold_panics() {
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 0;
+        let closed_failures_threshold = 2;
+        let _ = TimeCB::new(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_closed_failures_threshold_panics() {
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 0;
+        let _ = TimeCB::new(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+        );
+    }
+
+    #[test]
+    fn test_closed_success() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 2;
+        let mut cb = TimeCB::with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock.clone(),
+        );
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_closed_failure_stays_closed() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 2;
+        let mut cb = TimeCB::with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock.clone(),
+        );
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_closed_to_open() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 2;
+        let mut cb = TimeCB::with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock.clone(),
+        );
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_open_rejects_calls_immediately() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 2;
+        let mut cb = TimeCB::with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock.clone(),
+        );
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_open_rejects_until_timeout_then_allows_half_open_call() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 2;
+        let mut cb = TimeCB::with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock.clone(),
+        );
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        clock.tick();
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        clock.tick();
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_success_closes_breaker() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 2;
+        let mut cb = TimeCB::with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock.clone(),
+        );
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        clock.tick();
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        clock.tick();
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_failure_opens_breaker() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let open_timeout = Duration::from_millis(1);
+        let half_open_probes_threshold = 1;
+        let closed_failures_threshold = 2;
+        let mut cb = TimeCB::with_clock(
+            open_timeout,
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock.clone(),
+        );
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        clock.tick();
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        clock.tick();
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_half_open_respects_probes_threshold() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let half_open_probes_threshold = 2;
+        let closed_failures_threshold = 2;
+        let mut cb = TimeCB::with_clock(
+            Duration::from_millis(1),
+            half_open_probes_threshold,
+            closed_failures_threshold,
+            clock.clone(),
+        );
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        clock.tick();
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        clock.tick();
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_failures_until_trip_counts_down_to_open() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 3, clock.clone());
+        assert_eq!(cb.failures_until_trip(), 3);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 2);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 1);
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failures_until_trip(), 0);
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        clock.tick();
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failures_until_trip(), 3);
+    }
+
+    #[test]
+    fn test_call_reads_clock_at_most_once() {
+        let clock = CountingClock {
+            now: Rc::new(Cell::new(Instant::now())),
+            reads: Rc::new(Cell::new(0)),
+        };
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 2, 2, clock.clone());
+        let steps = [
+            (
+                Duration::ZERO,
+                Err::<(), ()>(()),
+                Err(CircuitError::Failed(())),
+            ),
+            (Duration::ZERO, Err(()), Err(CircuitError::Failed(()))),
+            (Duration::ZERO, Ok(()), Err(CircuitError::Rejected)),
+            (
+                Duration::from_millis(1),
+                Err(()),
+                Err(CircuitError::Failed(())),
+            ),
+            (Duration::ZERO, Err(()), Err(CircuitError::Failed(()))),
+            (Duration::from_millis(1), Ok(()), Ok(())),
+        ];
+
+        for (advance, outcome, expected) in steps {
+            clock.now.set(clock.now.get() + advance);
+            let reads_before = clock.reads.get();
+
+            let result = cb.call(|| outcome);
+            assert_eq!(result, expected);
+            assert!(clock.reads.get() - reads_before <= 1);
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log_records_on_trip_and_recovery() {
+        crate::cb::test_logger::install();
+        crate::cb::test_logger::take();
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone());
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        clock.tick();
+        let _ = cb.call(|| Ok::<(), ()>(()));
+
+        assert_eq!(
+            crate::cb::test_logger::take(),
+            vec![
+                (
+                    log::Level::Warn,
+                    "circuit breaker unnamed: closed -> open".to_string()
+                ),
+                (
+                    log::Level::Info,
+                    "circuit breaker unnamed: open -> half-open".to_string()
+                ),
+                (
+                    log::Level::Info,
+                    "circuit breaker unnamed: half-open -> closed".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_at_drives_state_machine() {
+        let start = Instant::now();
+        let open_timeout = Duration::from_millis(10);
+        let mut cb = TimeCB::new(open_timeout, 2, 2);
+
+        let result = cb.call_at(start, || Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let at = start + Duration::from_millis(1);
+        let result = cb.call_at(at, || Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call_at(at + open_timeout - Duration::from_nanos(1), || {
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let at = at + open_timeout;
+        let result = cb.call_at(at, || Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        let result = cb.call_at(at, || Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call_at(at + open_timeout - Duration::from_nanos(1), || {
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call_at(at + open_timeout, || Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_reset_from_open_executes_next_call() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 2, clock.clone());
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        cb.reset();
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let mut executed = false;
+        let result = cb.call(|| {
+            executed = true;
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert!(executed);
+
+        cb.reset();
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_transition_sender_receives_transitions_in_order() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let (tx, rx) = mpsc::channel();
+        let mut cb =
+            TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone()).transition_sender(tx);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+
+        clock.tick();
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+
+        clock.tick();
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+
+        let transitions: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            transitions,
+            vec![
+                (CircuitState::Closed, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Closed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trip_rejects_until_timeout() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(2), 1, 3, clock.clone());
+        cb.trip();
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let mut executed = false;
+        let result = cb.call(|| {
+            executed = true;
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert!(!executed);
+
+        clock.tick();
+        cb.trip();
+        clock.tick();
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+
+        clock.tick();
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_latency_rate_opens_without_errors() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 3, clock.clone())
+            .slow_call_rate(SlowCallRate {
+                slow_call_duration: Duration::from_millis(2),
+                window_size: 4,
+                slow_call_rate_percent: 50,
+            });
+
+        let result = cb.call(|| {
+            clock.tick();
+            clock.tick();
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| {
+            clock.tick();
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| {
+            clock.tick();
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| {
+            clock.tick();
+            clock.tick();
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+    }
+
+    #[test]
+    fn test_latency_rate_below_limit_stays_closed() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 3, clock.clone())
+            .slow_call_rate(SlowCallRate {
+                slow_call_duration: Duration::from_millis(2),
+                window_size: 3,
+                slow_call_rate_percent: 50,
+            });
+
+        for _ in 0..10 {
+            let result = cb.call(|| {
+                clock.tick();
+                clock.tick();
+                Ok::<(), ()>(())
+            });
+            assert_eq!(result, Ok(()));
+            for _ in 0..2 {
+                let result = cb.call(|| Ok::<(), ()>(()));
+                assert_eq!(result, Ok(()));
+            }
+            assert_eq!(cb.state(), CircuitState::Closed);
+        }
+    }
+
+    #[test]
+    fn test_latency_rate_window_starts_empty_after_recovery() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 3, clock.clone())
+            .slow_call_rate(SlowCallRate {
+                slow_call_duration: Duration::from_millis(1),
+                window_size: 2,
+                slow_call_rate_percent: 100,
+            });
+
+        for _ in 0..2 {
+            let result = cb.call(|| {
+                clock.tick();
+                Ok::<(), ()>(())
+            });
+            assert_eq!(result, Ok(()));
+        }
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        clock.tick();
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| {
+            clock.tick();
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_max_concurrent_rejects_until_probe_resolves() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 3, 1, clock.clone())
+            .half_open_max_concurrent(2);
+        cb.trip();
+        clock.tick();
+
+        assert!(cb.try_acquire());
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(cb.try_acquire());
+        assert!(!cb.try_acquire());
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(cb.try_acquire());
+        assert!(!cb.try_acquire());
+
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert!(cb.try_acquire());
+    }
+
+    #[test]
+    fn test_half_open_max_concurrent_drops_outcomes_after_reopen() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone())
+            .half_open_max_concurrent(2);
+        cb.trip();
+        clock.tick();
+
+        assert!(cb.try_acquire());
+        assert!(cb.try_acquire());
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(!cb.try_acquire());
+
+        clock.tick();
+        assert!(cb.try_acquire());
+        assert!(cb.try_acquire());
+        assert!(!cb.try_acquire());
+    }
+
+    #[test]
+    fn test_half_open_success_threshold_resets_on_failure() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 2, 1, clock.clone())
+            .half_open_success_threshold(2);
+        cb.trip();
+        clock.tick();
+
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_success_threshold_reopens_on_probe_threshold() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone())
+            .half_open_success_threshold(2);
+        cb.trip();
+        clock.tick();
+
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+    }
+
+    #[test]
+    fn test_latency_threshold_counts_overrun_success_as_failure() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 2, clock.clone())
+            .slow_call_threshold(Some(Duration::from_millis(1)));
+
+        let result = cb.call(|| {
+            clock.tick();
+            Ok::<u8, ()>(1)
+        });
+        assert_eq!(result, Ok(1));
+        assert_eq!(cb.failures_until_trip(), 2);
+
+        let result = cb.call(|| {
+            clock.tick();
+            clock.tick();
+            Ok::<u8, ()>(2)
+        });
+        assert_eq!(result, Err(CircuitError::TimedOut));
+        assert_eq!(cb.failures_until_trip(), 1);
+
+        let result = cb.call(|| {
+            clock.tick();
+            clock.tick();
+            Ok::<u8, ()>(3)
+        });
+        assert_eq!(result, Err(CircuitError::TimedOut));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_latency_threshold_none_disables() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 1, clock.clone())
+            .slow_call_threshold(None);
+
+        let result = cb.call(|| {
+            clock.tick();
+            clock.tick();
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_call_with_deadline_counts_overrun_as_failure() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 2, clock.clone());
+
+        let result = cb.call_with_deadline(Duration::from_millis(1), || {
+            clock.tick();
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.failures_until_trip(), 2);
+
+        let result = cb.call_with_deadline(Duration::from_millis(1), || {
+            clock.tick();
+            clock.tick();
+            Ok::<(), ()>(())
+        });
+        assert_eq!(result, Err(CircuitError::TimedOut));
+        assert_eq!(cb.failures_until_trip(), 1);
+
+        let result = cb.call_with_deadline(Duration::from_millis(1), || {
+            clock.tick();
+            clock.tick();
+            Err::<(), ()>(())
+        });
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call_with_deadline(Duration::from_millis(1), || Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+    }
+
+    #[test]
+    fn test_open_timeout_backoff_grows_to_cap_and_resets_on_close() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone())
+            .open_timeout_backoff(2, Duration::from_millis(5));
+        cb.trip();
+        assert_eq!(cb.effective_open_timeout(), Duration::from_millis(1));
+
+        for expected in [2, 4, 5] {
+            let open_for = cb.effective_open_timeout();
+            for _ in 0..open_for.as_millis() {
+                clock.tick();
+            }
+            assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+            assert_eq!(cb.state(), CircuitState::Open);
+            assert_eq!(cb.effective_open_timeout(), Duration::from_millis(expected));
+        }
+
+        for _ in 0..4 {
+            clock.tick();
+        }
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+        clock.tick();
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.effective_open_timeout(), Duration::from_millis(1));
+    }
+
+    #[derive(Debug)]
+    struct FixedRandom(f64);
+
+    impl RandomSource for FixedRandom {
+        fn next_f64(&mut self) -> f64 {
+            self.0
+        }
+    }
+
+    /// Steps evenly through `[0, 1)` in hundredths, so 100 draws admit exactly
+    /// the percentage a ramp allows.
+    #[derive(Debug, Default)]
+    struct CyclingRandom(u32);
+
+    impl RandomSource for CyclingRandom {
+        fn next_f64(&mut self) -> f64 {
+            let value = (f64::from(self.0 % 100) + 0.5) / 100.0;
+            self.0 += 1;
+            value
+        }
+    }
+
+    #[test]
+    fn test_open_timeout_jitter_is_deterministic_with_fixed_source() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut early = TimeCB::with_clock(Duration::from_millis(10), 1, 1, clock.clone())
+            .open_timeout_jitter(0.2, Box::new(FixedRandom(0.0)));
+        let mut late = TimeCB::with_clock(Duration::from_millis(10), 1, 1, clock.clone())
+            .open_timeout_jitter(0.2, Box::new(FixedRandom(0.75)));
+        early.trip();
+        late.trip();
+
+        for _ in 0..7 {
+            clock.tick();
+        }
+        assert_eq!(early.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+        clock.tick();
+        assert_eq!(early.call(|| Ok::<(), ()>(())), Ok(()));
+
+        for _ in 0..2 {
+            clock.tick();
+        }
+        assert_eq!(late.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+        clock.tick();
+        assert_eq!(late.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(late.effective_open_timeout(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_max_open_lifetime_forces_half_open_on_poll_without_calls() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone())
+            .open_timeout_backoff(10, Duration::from_millis(100))
+            .max_open_lifetime(Duration::from_millis(3));
+        cb.trip();
+        clock.tick();
+        cb.poll();
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.effective_open_timeout(), Duration::from_millis(10));
+
+        for _ in 0..2 {
+            clock.tick();
+            cb.poll();
+            assert_eq!(cb.state(), CircuitState::Open);
+        }
+        clock.tick();
+        assert_eq!(cb.state(), CircuitState::Open);
+        cb.poll();
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_poll_leaves_open_breaker_before_timeout() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(2), 1, 1, clock.clone());
+        cb.poll();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        cb.trip();
+        clock.tick();
+        cb.poll();
+        assert_eq!(cb.state(), CircuitState::Open);
+        clock.tick();
+        cb.poll();
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn test_metrics_after_known_sequence() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone());
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert!(!cb.try_acquire());
+        clock.tick();
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+
+        assert_eq!(
+            cb.metrics(),
+            Metrics {
+                succeeded: 3,
+                failed: 1,
+                rejected: 2,
+                opens: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_on_transition_records_each_transition_once() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let transitions = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = transitions.clone();
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone())
+            .on_transition(move |from, to| sink.borrow_mut().push((from, to)));
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        clock.tick();
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+
+        assert_eq!(
+            *transitions.borrow(),
+            vec![
+                (CircuitState::Closed, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Closed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_time_in_state_restarts_on_each_transition() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(2), 1, 1, clock.clone());
+        clock.tick();
+        clock.tick();
+        clock.tick();
+        assert_eq!(cb.time_in_state(), Duration::from_millis(3));
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.time_in_state(), Duration::ZERO);
+        clock.tick();
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.time_in_state(), Duration::from_millis(1));
+
+        clock.tick();
+        cb.poll();
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        clock.tick();
+        assert_eq!(cb.time_in_state(), Duration::from_millis(1));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.time_in_state(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_is_call_permitted_matches_call() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(2), 1, 1, clock.clone());
+        let steps = [
+            (false, Err(())),
+            (false, Ok(())),
+            (true, Ok(())),
+            (true, Err(())),
+            (true, Ok(())),
+            (true, Ok(())),
+            (false, Ok(())),
+        ];
+        for (tick, outcome) in steps {
+            if tick {
+                clock.tick();
+            }
+            let permitted = cb.is_call_permitted();
+            let state = cb.state();
+            assert_eq!(cb.is_call_permitted(), permitted);
+            assert_eq!(cb.state(), state);
+
+            let mut executed = false;
+            let _ = cb.call(|| {
+                executed = true;
+                outcome
+            });
+            assert_eq!(executed, permitted);
+        }
+    }
+
+    #[test]
+    fn test_disabled_ignores_long_failure_run() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 2, clock.clone());
+        cb.disable();
+        for _ in 0..100 {
+            assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+            assert!(cb.try_acquire());
+            cb.record_failure();
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.failures_until_trip(), 2);
+        assert_eq!(cb.metrics(), Metrics::default());
+
+        cb.enable();
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_time_until_half_open_before_at_and_after_timeout() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(2), 1, 1, clock.clone());
+        assert_eq!(cb.time_until_half_open(), None);
+        cb.trip();
+
+        clock.tick();
+        assert_eq!(cb.time_until_half_open(), Some(Duration::from_millis(1)));
+        clock.tick();
+        assert_eq!(cb.time_until_half_open(), Some(Duration::ZERO));
+        clock.tick();
+        assert_eq!(cb.time_until_half_open(), Some(Duration::ZERO));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        cb.poll();
+        assert_eq!(cb.time_until_half_open(), None);
+    }
+
+    #[test]
+    fn test_call_classified_ignored_errors_never_open() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone())
+            .half_open_max_concurrent(1);
+        for _ in 0..100 {
+            let result = cb.call_classified(|| Err::<(), u16>(404), |status| *status >= 500);
+            assert_eq!(result, Err(CircuitError::Failed(404)));
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        cb.trip();
+        clock.tick();
+        let _ = cb.call_classified(|| Err::<(), u16>(404), |status| *status >= 500);
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(cb.is_call_permitted());
+        let _ = cb.call_classified(|| Err::<(), u16>(503), |status| *status >= 500);
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_logical_clock_drives_state_machine() {
+        let mut cb = TimeCB::with_clock(Duration::from_millis(5), 1, 1, LogicalClock::new());
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        cb.clock().advance(Duration::from_millis(4));
+        assert_eq!(cb.time_until_half_open(), Some(Duration::from_millis(1)));
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+
+        cb.clock().advance(Duration::from_millis(1));
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.clock().now(), LogicalInstant(5_000_000));
+    }
+
+    #[test]
+    fn test_failure_count_and_half_open_attempts() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 3, 3, clock.clone());
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.failure_count(), 2);
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.failure_count(), 0);
+
+        cb.trip();
+        clock.tick();
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert_eq!(cb.half_open_attempts(), 1);
+    }
+
+    #[test]
+    fn test_threshold_above_u8_opens_at_exactly_threshold() {
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1000, LogicalClock::new());
+        for _ in 0..999 {
+            let _ = cb.call(|| Err::<(), ()>(()));
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_half_open_timeout_reopens_on_following_call() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone())
+            .half_open_max_concurrent(1)
+            .half_open_timeout(Duration::from_millis(2));
+        cb.trip();
+        clock.tick();
+
+        assert!(cb.try_acquire());
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        clock.tick();
+        assert!(!cb.try_acquire());
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        clock.tick();
+        assert!(!cb.is_call_permitted());
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Open);
+        clock.tick();
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
 
-    use std::cell::Cell;
-    use std::rc::Rc;
+    #[test]
+    fn test_call_or_else_falls_back_only_while_open() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(2), 1, 1, clock.clone());
+        assert_eq!(cb.call_or_else(|| Ok::<u8, ()>(1), || 0), Ok(1));
+        assert_eq!(cb.call_or_else(|| Err::<u8, ()>(()), || 0), Err(()));
+        assert_eq!(cb.state(), CircuitState::Open);
 
-    #[derive(Debug, Clone)]
-    struct TestClock {
-        now: Rc<Cell<Instant>>,
+        let mut executed = false;
+        let result = cb.call_or_else(
+            || {
+                executed = true;
+                Ok::<u8, ()>(1)
+            },
+            || 0,
+        );
+        assert_eq!(result, Ok(0));
+        assert!(!executed);
+
+        clock.tick();
+        assert_eq!(cb.call_or_else(|| Ok::<u8, ()>(1), || 0), Ok(0));
+        clock.tick();
+        assert_eq!(cb.call_or_else(|| Ok::<u8, ()>(1), || 0), Ok(1));
+        assert_eq!(cb.state(), CircuitState::Closed);
     }
 
-    impl Clock for TestClock {
-        fn now(&self) -> Instant {
-            self.now.get()
-        }
+    #[test]
+    fn test_event_log_stamps_events_with_clock() {
+        let mut cb =
+            TimeCB::with_clock(Duration::from_millis(2), 1, 1, LogicalClock::new()).event_log(2);
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.events().count(), 2);
+        cb.clock().advance(Duration::from_millis(1));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.events().count(), 2);
+        cb.clock().advance(Duration::from_millis(1));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+
+        let events: Vec<_> = cb.events().cloned().collect();
+        assert_eq!(
+            events,
+            vec![
+                Event {
+                    at: LogicalInstant(1_000_000),
+                    result: CircuitResult::Rejected,
+                    state: CircuitState::Open,
+                },
+                Event {
+                    at: LogicalInstant(2_000_000),
+                    result: CircuitResult::Succeeded,
+                    state: CircuitState::Closed,
+                },
+            ]
+        );
     }
 
-    impl TestClock {
-        const TICK: Duration = Duration::from_millis(1);
+    #[test]
+    fn test_recovery_ramp_admits_growing_share() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 1, 1, clock.clone())
+            .recovery_strategy(
+                RecoveryStrategy::Ramp {
+                    duration: Duration::from_millis(100),
+                },
+                Box::new(CyclingRandom::default()),
+            );
+        cb.trip();
+        clock.tick();
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
 
-        fn new(start: Instant) -> Self {
-            Self {
-                now: Rc::new(Cell::new(start)),
+        let mut admitted = Vec::new();
+        for _ in 0..10 {
+            let calls = (0..100)
+                .filter(|_| cb.call(|| Ok::<(), ()>(())).is_ok())
+                .count();
+            admitted.push(calls);
+            for _ in 0..10 {
+                clock.tick();
             }
         }
+        assert_eq!(admitted, vec![0, 10, 20, 30, 40, 50, 60, 70, 80, 90]);
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
 
-        fn tick(&self) {
-            self.now.set(self.now.get() + Self::TICK);
-        }
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
     }
 
     #[test]
-    #[should_panic]
-    fn test_zero_open_timeout_panics() {
-        let open_timeout = Duration::from_millis(0);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 2;
-        let _ = TimeCB::new(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-        );
+    fn test_failure_during_recovery_ramp_reopens() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 3, 1, clock.clone())
+            .recovery_strategy(
+                RecoveryStrategy::Ramp {
+                    duration: Duration::from_millis(10),
+                },
+                Box::new(FixedRandom(0.0)),
+            );
+        cb.trip();
+        clock.tick();
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+
+        clock.tick();
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
     }
 
     #[test]
-    #[should_panic]
-    fn test_zero_half_open_probes_threshold_panics() {
-        let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 0;
-        let closed_failures_threshold = 2;
-        let _ = TimeCB::new(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
+    fn test_overrun_success_returns_timed_out() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 3, clock.clone())
+            .slow_call_threshold(Some(Duration::from_millis(1)))
+            .event_log(4);
+
+        let result = cb.call(|| {
+            clock.tick();
+            clock.tick();
+            Ok::<u8, ()>(1)
+        });
+        assert_eq!(result, Err(CircuitError::TimedOut));
+        assert_eq!(CircuitResult::from(&result), CircuitResult::TimedOut);
+        assert_eq!(cb.metrics().failed, 1);
+        assert_eq!(cb.metrics().succeeded, 0);
+        let result = cb.call(|| Err::<u8, ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        let result = cb.call(|| Ok::<u8, ()>(2));
+        assert_eq!(result, Ok(2));
+
+        let results: Vec<_> = cb.events().map(|event| event.result.clone()).collect();
+        assert_eq!(
+            results,
+            vec![
+                CircuitResult::TimedOut,
+                CircuitResult::Failed,
+                CircuitResult::Succeeded,
+            ]
         );
+        assert_eq!(cb.metrics().failed, 2);
+        assert_eq!(cb.metrics().succeeded, 1);
     }
 
     #[test]
     #[should_panic]
-    fn test_zero_closed_failures_threshold_panics() {
-        let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 0;
-        let _ = TimeCB::new(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-        );
+    fn test_set_zero_open_timeout_panics() {
+        let mut cb = TimeCB::new(Duration::from_millis(10), 1, 1);
+        cb.set_open_timeout(Duration::ZERO);
     }
 
     #[test]
-    fn test_closed_success() {
+    fn test_set_open_timeout_applies_to_next_open_period() {
         let start = Instant::now();
         let clock = TestClock::new(start);
-        let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 2;
-        let mut cb = TimeCB::with_clock(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
-        );
-        assert_eq!(cb.state(), CircuitState::Closed);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 1, clock.clone());
+        cb.set_open_timeout(Duration::from_millis(50));
+        assert_eq!(cb.open_timeout(), Duration::from_millis(50));
 
-        let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Succeeded);
-        assert_eq!(cb.state(), CircuitState::Closed);
+        cb.trip();
+        assert_eq!(cb.time_until_half_open(), Some(Duration::from_millis(50)));
+
+        cb.set_open_timeout(Duration::from_millis(20));
+        assert_eq!(cb.time_until_half_open(), Some(Duration::from_millis(50)));
+        assert_eq!(cb.effective_open_timeout(), Duration::from_millis(20));
+
+        cb.reset();
+        cb.trip();
+        assert_eq!(cb.time_until_half_open(), Some(Duration::from_millis(20)));
     }
 
     #[test]
-    fn test_closed_failure_stays_closed() {
+    fn test_set_open_timeout_keeps_backoff() {
         let start = Instant::now();
         let clock = TestClock::new(start);
-        let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 2;
-        let mut cb = TimeCB::with_clock(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
-        );
-        assert_eq!(cb.state(), CircuitState::Closed);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 1, clock.clone())
+            .open_timeout_backoff(4, Duration::from_millis(100));
+        cb.trip();
+        for _ in 0..10 {
+            clock.tick();
+        }
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.effective_open_timeout(), Duration::from_millis(40));
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
-        assert_eq!(cb.state(), CircuitState::Closed);
+        cb.set_open_timeout(Duration::from_millis(20));
+        assert_eq!(cb.effective_open_timeout(), Duration::from_millis(40));
+        cb.set_open_timeout(Duration::from_millis(60));
+        assert_eq!(cb.effective_open_timeout(), Duration::from_millis(60));
     }
 
     #[test]
-    fn test_closed_to_open() {
+    fn test_heal_half_open_probes_forgives_intermittent_failures() {
         let start = Instant::now();
+        let outcomes = [true, false, true, false, true, true];
+
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 2, 1, clock.clone())
+            .half_open_success_threshold(2);
+        cb.trip();
+        clock.tick();
+        let states: Vec<_> = outcomes[..4]
+            .iter()
+            .map(|succeed| {
+                let _ = cb.call(|| if *succeed { Ok(()) } else { Err(()) });
+                cb.state()
+            })
+            .collect();
+        assert_eq!(states.last(), Some(&CircuitState::Open));
+
         let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(1), 2, 1, clock.clone())
+            .half_open_success_threshold(2)
+            .heal_half_open_probes();
+        cb.trip();
+        clock.tick();
+        let states: Vec<_> = outcomes
+            .iter()
+            .map(|succeed| {
+                let _ = cb.call(|| if *succeed { Ok(()) } else { Err(()) });
+                cb.state()
+            })
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                CircuitState::HalfOpen,
+                CircuitState::HalfOpen,
+                CircuitState::HalfOpen,
+                CircuitState::HalfOpen,
+                CircuitState::HalfOpen,
+                CircuitState::Closed,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_try_new_reports_each_zero_setting() {
         let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 2;
-        let mut cb = TimeCB::with_clock(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
+        assert_eq!(
+            TimeCB::try_new(Duration::ZERO, 1, 1).err(),
+            Some(BuildError::ZeroOpenTimeout)
         );
-        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(
+            TimeCB::try_new(open_timeout, 1, 0).err(),
+            Some(BuildError::ZeroFailureThreshold)
+        );
+        assert_eq!(
+            TimeCB::try_new(open_timeout, 0, 1).err(),
+            Some(BuildError::ZeroHalfOpenThreshold)
+        );
+        assert!(TimeCB::try_new(open_timeout, 1, 1).is_ok());
+    }
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
-        assert_eq!(cb.state(), CircuitState::Closed);
+    #[test]
+    fn test_open_probe_fraction_admits_configured_share() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_secs(60), 1, 1, clock.clone())
+            .open_probe_fraction(0.05, Box::new(CyclingRandom::default()));
+        cb.trip();
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        let admitted = (0..1000)
+            .filter(|_| cb.call(|| Err::<(), ()>(())) != Err(CircuitError::Rejected))
+            .count();
+        assert_eq!(admitted, 50);
         assert_eq!(cb.state(), CircuitState::Open);
     }
 
     #[test]
-    fn test_open_rejects_calls_immediately() {
+    fn test_successful_open_probe_closes_before_timeout() {
         let start = Instant::now();
         let clock = TestClock::new(start);
-        let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 2;
-        let mut cb = TimeCB::with_clock(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
-        );
-        assert_eq!(cb.state(), CircuitState::Closed);
+        let mut cb = TimeCB::with_clock(Duration::from_secs(60), 1, 1, clock.clone())
+            .open_probe_fraction(0.5, Box::new(CyclingRandom(50)));
+        cb.trip();
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        for _ in 0..50 {
+            assert_eq!(cb.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+        }
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
         assert_eq!(cb.state(), CircuitState::Closed);
+    }
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
-        assert_eq!(cb.state(), CircuitState::Open);
+    #[test]
+    fn test_acquire_record_drops_outcomes_while_open() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb =
+            TimeCB::with_clock(Duration::from_millis(10), 1, 1, clock.clone()).event_log(4);
+        assert!(cb.try_acquire());
+        assert!(cb.try_acquire());
+        assert!(cb.try_acquire());
 
-        let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Rejected);
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+        cb.record_failure();
+        cb.record_success();
         assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.metrics().failed, 1);
+        assert_eq!(cb.metrics().succeeded, 0);
+        assert_eq!(cb.events().count(), 1);
     }
 
     #[test]
-    fn test_open_rejects_until_timeout_then_allows_half_open_call() {
+    fn test_failed_open_probes_keep_open_without_backoff() {
         let start = Instant::now();
         let clock = TestClock::new(start);
-        let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 2;
-        let mut cb = TimeCB::with_clock(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
-        );
-        assert_eq!(cb.state(), CircuitState::Closed);
-
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
-        assert_eq!(cb.state(), CircuitState::Closed);
-
-        clock.tick();
+        let mut cb = TimeCB::with_clock(Duration::from_secs(60), 3, 1, clock.clone())
+            .half_open_max_concurrent(u32::MAX)
+            .open_probe_fraction(0.05, Box::new(CyclingRandom::default()));
+        cb.trip();
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        let admitted = (0..1000)
+            .filter(|_| cb.call(|| Err::<(), ()>(())) != Err(CircuitError::Rejected))
+            .count();
+        assert_eq!(admitted, 50);
         assert_eq!(cb.state(), CircuitState::Open);
-
-        clock.tick();
-
-        let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Succeeded);
-        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.metrics().opens, 1);
+        assert_eq!(cb.metrics().failed, 50);
+        assert_eq!(cb.time_until_half_open(), Some(Duration::from_secs(60)));
     }
 
     #[test]
-    fn test_half_open_success_closes_breaker() {
+    fn test_open_probes_close_after_success_threshold() {
         let start = Instant::now();
         let clock = TestClock::new(start);
-        let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 2;
-        let mut cb = TimeCB::with_clock(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
-        );
-        assert_eq!(cb.state(), CircuitState::Closed);
+        let mut cb = TimeCB::with_clock(Duration::from_secs(60), 3, 1, clock.clone())
+            .half_open_success_threshold(2)
+            .open_probe_fraction(0.5, Box::new(CyclingRandom(50)));
+        cb.trip();
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        for _ in 0..50 {
+            assert_eq!(cb.call(|| Ok::<(), ()>(())), Err(CircuitError::Rejected));
+        }
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.call(|| Err::<(), ()>(())), Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
         assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.metrics().opens, 1);
+    }
 
-        clock.tick();
+    #[test]
+    fn test_ignored_open_probe_error_releases_its_slot() {
+        let start = Instant::now();
+        let clock = TestClock::new(start);
+        let mut cb = TimeCB::with_clock(Duration::from_secs(60), 3, 1, clock.clone())
+            .half_open_success_threshold(2)
+            .open_probe_fraction(0.5, Box::new(FixedRandom(0.0)));
+        cb.trip();
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        let result = cb.call_classified(|| Err::<(), ()>(()), |_| false);
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
         assert_eq!(cb.state(), CircuitState::Open);
 
-        clock.tick();
-
-        let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Succeeded);
+        cb.record_failure();
+        assert_eq!(cb.metrics().failed, 0);
+        assert_eq!(cb.call(|| Ok::<(), ()>(())), Ok(()));
         assert_eq!(cb.state(), CircuitState::Closed);
     }
 
     #[test]
-    fn test_half_open_failure_opens_breaker() {
+    fn test_call_or_else_keeps_value_of_overrun_call() {
         let start = Instant::now();
         let clock = TestClock::new(start);
-        let open_timeout = Duration::from_millis(1);
-        let half_open_probes_threshold = 1;
-        let closed_failures_threshold = 2;
-        let mut cb = TimeCB::with_clock(
-            open_timeout,
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
-        );
-        assert_eq!(cb.state(), CircuitState::Closed);
-
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
-        assert_eq!(cb.state(), CircuitState::Closed);
-
-        clock.tick();
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 1, clock.clone())
+            .slow_call_threshold(Some(Duration::from_millis(1)));
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        let mut fallback_ran = false;
+        let result = cb.call_or_else(
+            || {
+                clock.tick();
+                clock.tick();
+                Ok::<u8, ()>(1)
+            },
+            || {
+                fallback_ran = true;
+                0
+            },
+        );
+        assert_eq!(result, Ok(1));
+        assert!(!fallback_ran);
+        assert_eq!(cb.metrics().failed, 1);
         assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.call_or_else(|| Ok::<u8, ()>(1), || 0), Ok(0));
+    }
 
-        clock.tick();
-
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
-        assert_eq!(cb.state(), CircuitState::Open);
+    fn acquire_matching_permitted<C: Clock>(cb: &mut TimeCB<C>) -> bool {
+        let permitted = cb.is_call_permitted();
+        let acquired = cb.try_acquire();
+        assert_eq!(permitted, acquired);
+        acquired
     }
 
     #[test]
-    fn test_half_open_respects_probes_threshold() {
+    fn test_is_call_permitted_matches_try_acquire() {
         let start = Instant::now();
         let clock = TestClock::new(start);
-        let half_open_probes_threshold = 2;
-        let closed_failures_threshold = 2;
-        let mut cb = TimeCB::with_clock(
-            Duration::from_millis(1),
-            half_open_probes_threshold,
-            closed_failures_threshold,
-            clock.clone(),
-        );
-        assert_eq!(cb.state(), CircuitState::Closed);
-
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
-        assert_eq!(cb.state(), CircuitState::Closed);
-
-        clock.tick();
-
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        let mut cb = TimeCB::with_clock(Duration::from_millis(10), 1, 1, clock.clone())
+            .half_open_max_concurrent(1)
+            .open_probe_fraction(0.5, Box::new(CyclingRandom(40)))
+            .recovery_strategy(
+                RecoveryStrategy::Ramp {
+                    duration: Duration::from_millis(10),
+                },
+                Box::new(CyclingRandom::default()),
+            );
+        assert!(acquire_matching_permitted(&mut cb));
+        cb.record_failure();
         assert_eq!(cb.state(), CircuitState::Open);
 
-        clock.tick();
+        let mut probes = 0;
+        for _ in 0..20 {
+            if acquire_matching_permitted(&mut cb) {
+                probes += 1;
+                cb.record_failure();
+            }
+        }
+        assert_eq!(probes, 10);
+        assert_eq!(cb.state(), CircuitState::Open);
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
+        for _ in 0..10 {
+            clock.tick();
+        }
+        assert!(acquire_matching_permitted(&mut cb));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(!acquire_matching_permitted(&mut cb));
+        cb.record_success();
         assert_eq!(cb.state(), CircuitState::HalfOpen);
 
-        let result = cb.call(|| Err::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Failed);
-        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(!acquire_matching_permitted(&mut cb));
+        for _ in 0..5 {
+            clock.tick();
+        }
+        let mut admitted = 0;
+        for _ in 0..60 {
+            if acquire_matching_permitted(&mut cb) {
+                admitted += 1;
+                cb.record_success();
+            }
+        }
+        assert_eq!(admitted, 49);
 
-        let result = cb.call(|| Ok::<(), ()>(()));
-        assert_eq!(result, CircuitResult::Rejected);
-        assert_eq!(cb.state(), CircuitState::Open);
+        for _ in 0..5 {
+            clock.tick();
+        }
+        assert!(acquire_matching_permitted(&mut cb));
+        assert_eq!(cb.state(), CircuitState::Closed);
     }
 }
@@ -0,0 +1,291 @@
+//! Sliding-window Circuit Breaker implementation
+
+use crate::cb::{CircuitBreaker, CircuitError, CircuitState};
+use crate::windowed::{TripCondition, WindowedCB};
+
+/// Opens when `failure_threshold` of the last `window_size` calls failed, whether
+/// or not successes were interleaved. Open and half-open behave like `CountCB`:
+/// `half_open_threshold` rejected calls lead to a single probe.
+///
+/// With `min_throughput`, the window must also hold that many calls before it
+/// can open, so a quiet service is not tripped by its first few failures.
+#[derive(Debug)]
+pub struct SlidingWindowCB {
+    inner: WindowedCB<FailureCount>,
+}
+
+#[derive(Debug)]
+struct FailureCount {
+    failure_threshold: u32,
+    min_throughput: u32,
+}
+
+impl TripCondition for FailureCount {
+    fn reached(&self, calls: u32, failures: u32) -> bool {
+        failures >= self.failure_threshold && calls >= self.min_throughput
+    }
+}
+
+impl SlidingWindowCB {
+    pub fn new(window_size: u32, failure_threshold: u32, half_open_threshold: u32) -> Self {
+        assert!(failure_threshold > 0);
+        assert!(failure_threshold <= window_size);
+
+        let trip = FailureCount {
+            failure_threshold,
+            min_throughput: 0,
+        };
+        SlidingWindowCB {
+            inner: WindowedCB::new(window_size, trip, half_open_threshold),
+        }
+    }
+
+    /// Keeps the breaker closed until the window holds `min_throughput` calls.
+    pub fn min_throughput(mut self, min_throughput: u32) -> Self {
+        assert!(min_throughput <= self.inner.window_size());
+
+        self.inner.trip_mut().min_throughput = min_throughput;
+        self
+    }
+}
+
+impl CircuitBreaker for SlidingWindowCB {
+    fn call_classified<F, R, E, P>(
+        &mut self,
+        f: F,
+        should_count_failure: P,
+    ) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+        P: FnOnce(&E) -> bool,
+    {
+        self.inner.call_classified(f, should_count_failure)
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        self.inner.try_acquire()
+    }
+
+    fn record_success(&mut self) {
+        self.inner.record_success();
+    }
+
+    fn record_failure(&mut self) {
+        self.inner.record_failure();
+    }
+
+    fn state(&self) -> CircuitState {
+        self.inner.state()
+    }
+
+    fn is_call_permitted(&self) -> bool {
+        self.inner.is_call_permitted()
+    }
+
+    fn version(&self) -> u64 {
+        self.inner.version()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn trip(&mut self) {
+        self.inner.trip();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::count::CountCB;
+
+    #[test]
+    #[should_panic]
+    fn test_zero_window_size_panics() {
+        SlidingWindowCB::new(0, 1, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_failure_threshold_panics() {
+        SlidingWindowCB::new(5, 0, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_half_open_threshold_panics() {
+        SlidingWindowCB::new(5, 1, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_threshold_above_window_panics() {
+        SlidingWindowCB::new(5, 6, 1);
+    }
+
+    #[test]
+    fn test_interleaved_failures_open_where_count_cb_does_not() {
+        let pattern = [false, false, true];
+        let mut window_cb = SlidingWindowCB::new(5, 3, 1);
+        let mut count_cb = CountCB::new(3, 1);
+
+        for succeed in pattern.iter().cycle().take(30) {
+            let _ = count_cb.call(|| if *succeed { Ok(()) } else { Err(()) });
+            assert_eq!(count_cb.state(), CircuitState::Closed);
+        }
+
+        let results: Vec<_> = pattern
+            .iter()
+            .cycle()
+            .take(4)
+            .map(|succeed| window_cb.call(|| if *succeed { Ok(()) } else { Err(()) }))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                Err(CircuitError::Failed(())),
+                Err(CircuitError::Failed(())),
+                Ok(()),
+                Err(CircuitError::Failed(())),
+            ]
+        );
+        assert_eq!(window_cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_old_failures_slide_out_of_window() {
+        let mut cb = SlidingWindowCB::new(3, 2, 1);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+
+        for _ in 0..3 {
+            let result = cb.call(|| Ok::<(), ()>(()));
+            assert_eq!(result, Ok(()));
+        }
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_open_half_open_closed_cycle() {
+        let mut cb = SlidingWindowCB::new(2, 1, 2);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_trip_and_reset() {
+        let mut cb = SlidingWindowCB::new(3, 2, 1);
+        let _ = cb.call(|| Err::<(), ()>(()));
+
+        cb.trip();
+        assert_eq!(cb.state(), CircuitState::Open);
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Rejected));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        cb.reset();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_version_bumps_only_on_transitions() {
+        let mut cb = SlidingWindowCB::new(3, 2, 1);
+        assert_eq!(cb.version(), 0);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        let _ = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(cb.version(), 0);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.version(), 1);
+
+        cb.reset();
+        cb.reset();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.version(), 2);
+    }
+
+    #[test]
+    fn test_call_classified_ignored_errors_stay_out_of_window() {
+        let mut cb = SlidingWindowCB::new(3, 2, 1);
+        let _ = cb.call(|| Err::<(), bool>(true));
+        for _ in 0..10 {
+            let _ = cb.call_classified(|| Err::<(), bool>(false), |counted| *counted);
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+        let _ = cb.call(|| Err::<(), bool>(true));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_min_throughput_above_window_panics() {
+        let _ = SlidingWindowCB::new(5, 1, 1).min_throughput(6);
+    }
+
+    #[test]
+    fn test_min_throughput_keeps_quiet_service_closed() {
+        let mut cb = SlidingWindowCB::new(20, 1, 1).min_throughput(20);
+
+        let result = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(result, Err(CircuitError::Failed(())));
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        for _ in 0..18 {
+            let result = cb.call(|| Ok::<(), ()>(()));
+            assert_eq!(result, Ok(()));
+            assert_eq!(cb.state(), CircuitState::Closed);
+        }
+
+        let result = cb.call(|| Ok::<(), ()>(()));
+        assert_eq!(result, Ok(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_threshold_above_u8_opens_at_exactly_threshold() {
+        let mut cb = SlidingWindowCB::new(2000, 1000, 1);
+        for _ in 0..999 {
+            let _ = cb.call(|| Err::<(), ()>(()));
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        let _ = cb.call(|| Err::<(), ()>(()));
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+}
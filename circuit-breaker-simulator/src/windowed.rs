@@ -0,0 +1,197 @@
+//! Outcome window and state machine shared by `SlidingWindowCB` and `RateCB`
+
+use crate::cb::{CircuitBreaker, CircuitError, CircuitState};
+use alloc::collections::VecDeque;
+
+/// Decides from the calls in a closed breaker's window whether it opens.
+pub(crate) trait TripCondition {
+    /// Whether `failures` out of the `calls` in the window open the breaker.
+    fn reached(&self, calls: u32, failures: u32) -> bool;
+}
+
+/// The last `window_size` outcomes of a closed breaker, opening once `trip` is
+/// reached. Open and half-open behave like `CountCB`: `half_open_threshold`
+/// rejected calls lead to a single probe.
+#[derive(Debug)]
+pub(crate) struct WindowedCB<T> {
+    state: CircuitState,
+    // true for a failed call, oldest first.
+    outcomes: VecDeque<bool>,
+    window_size: u32,
+    window_failures: u32,
+    trip: T,
+    half_open_attempts: u32,
+    half_open_threshold: u32,
+    version: u64,
+}
+
+impl<T: TripCondition> WindowedCB<T> {
+    pub(crate) fn new(window_size: u32, trip: T, half_open_threshold: u32) -> Self {
+        assert!(window_size > 0);
+        assert!(half_open_threshold > 0);
+
+        WindowedCB {
+            state: CircuitState::Closed,
+            outcomes: VecDeque::with_capacity(window_size as usize),
+            window_size,
+            window_failures: 0,
+            trip,
+            half_open_attempts: 0,
+            half_open_threshold,
+            version: 0,
+        }
+    }
+
+    pub(crate) fn window_size(&self) -> u32 {
+        self.window_size
+    }
+
+    pub(crate) fn trip_mut(&mut self) -> &mut T {
+        &mut self.trip
+    }
+
+    fn trip_reached(&self) -> bool {
+        assert!(self.window_failures as usize <= self.outcomes.len());
+
+        self.trip
+            .reached(self.outcomes.len() as u32, self.window_failures)
+    }
+
+    fn open(&mut self) {
+        self.transition(CircuitState::Open);
+        self.outcomes.clear();
+        self.window_failures = 0;
+        self.half_open_attempts = 0;
+    }
+
+    fn transition(&mut self, to: CircuitState) {
+        assert!(self.state != to);
+
+        self.state = to;
+        self.version += 1;
+    }
+
+    fn push_outcome(&mut self, failed: bool) {
+        if self.outcomes.len() == self.window_size as usize {
+            let evicted_failure = self.outcomes.pop_front().unwrap();
+            if evicted_failure {
+                self.window_failures -= 1;
+            }
+        }
+        self.outcomes.push_back(failed);
+        if failed {
+            self.window_failures += 1;
+        }
+    }
+}
+
+impl<T: TripCondition> CircuitBreaker for WindowedCB<T> {
+    fn call_classified<F, R, E, P>(
+        &mut self,
+        f: F,
+        should_count_failure: P,
+    ) -> Result<R, CircuitError<E>>
+    where
+        F: FnOnce() -> Result<R, E>,
+        P: FnOnce(&E) -> bool,
+    {
+        if !self.try_acquire() {
+            return Err(CircuitError::Rejected);
+        }
+
+        match f() {
+            Ok(value) => {
+                self.record_success();
+                Ok(value)
+            }
+            Err(error) => {
+                if should_count_failure(&error) {
+                    self.record_failure();
+                }
+                Err(CircuitError::Failed(error))
+            }
+        }
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        match self.state {
+            CircuitState::Closed => {
+                assert!(self.outcomes.len() <= self.window_size as usize);
+                assert!(!self.trip_reached());
+                assert!(self.half_open_attempts == 0);
+                true
+            }
+            CircuitState::Open => {
+                assert!(self.outcomes.is_empty());
+                assert!(self.half_open_attempts < self.half_open_threshold);
+
+                self.half_open_attempts += 1;
+                if self.half_open_attempts == self.half_open_threshold {
+                    self.transition(CircuitState::HalfOpen);
+                    self.half_open_attempts = 0;
+                }
+                false
+            }
+            CircuitState::HalfOpen => {
+                assert!(self.outcomes.is_empty());
+                assert!(self.half_open_attempts == 0);
+                true
+            }
+        }
+    }
+
+    fn record_success(&mut self) {
+        match self.state {
+            CircuitState::Closed => {
+                self.push_outcome(false);
+                if self.trip_reached() {
+                    self.open();
+                }
+            }
+            CircuitState::HalfOpen => self.transition(CircuitState::Closed),
+            CircuitState::Open => {}
+        }
+    }
+
+    fn record_failure(&mut self) {
+        match self.state {
+            CircuitState::Closed => {
+                self.push_outcome(true);
+                if self.trip_reached() {
+                    self.open();
+                }
+            }
+            CircuitState::HalfOpen => self.open(),
+            CircuitState::Open => {}
+        }
+    }
+
+    fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    fn is_call_permitted(&self) -> bool {
+        self.state != CircuitState::Open
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn reset(&mut self) {
+        if self.state != CircuitState::Closed {
+            self.transition(CircuitState::Closed);
+        }
+        self.outcomes.clear();
+        self.window_failures = 0;
+        self.half_open_attempts = 0;
+    }
+
+    fn trip(&mut self) {
+        if self.state == CircuitState::Open {
+            self.half_open_attempts = 0;
+        } else {
+            self.open();
+        }
+    }
+}
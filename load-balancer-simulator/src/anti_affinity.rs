@@ -0,0 +1,201 @@
+use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Wraps a strategy so that a server picked within the last `anti_affinity_window`
+/// selections is skipped while another healthy server is available.
+///
+/// Skipping works by asking the inner strategy again, at most `count()` times, so
+/// any bookkeeping the inner strategy does for a discarded pick (such as a
+/// connection count) is kept. When every attempt lands on a recent server, the
+/// last attempt is returned.
+pub struct AntiAffinity<L> {
+    inner: L,
+    anti_affinity_window: usize,
+    recent: VecDeque<usize>,
+}
+
+impl<L> AntiAffinity<L> {
+    pub fn new(inner: L, anti_affinity_window: usize) -> Self {
+        assert!(anti_affinity_window > 0);
+
+        Self {
+            inner,
+            anti_affinity_window,
+            recent: VecDeque::with_capacity(anti_affinity_window),
+        }
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn select<T>(&mut self, exclude: &[usize]) -> LoadBalancerResult
+    where
+        L: LoadBalancer<T>,
+    {
+        assert!(self.recent.len() <= self.anti_affinity_window);
+
+        let mut result = self.inner.select_excluding(exclude);
+        for _ in 1..self.inner.count() {
+            match result {
+                LoadBalancerResult::Selected { id } if self.recent.contains(&id) => {
+                    result = self.inner.select_excluding(exclude);
+                }
+                _ => break,
+            }
+        }
+
+        if let LoadBalancerResult::Selected { id } = result {
+            if self.recent.len() == self.anti_affinity_window {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(id);
+        }
+        result
+    }
+}
+
+impl<T, L: LoadBalancer<T>> LoadBalancer<T> for AntiAffinity<L> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        self.select(&[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        self.select(exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        self.inner.healthy_server(server_id);
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        self.inner.unhealthy_server(server_id);
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        self.inner.drain_server(server_id);
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        self.inner.set_states(states);
+    }
+
+    fn record_latency(&mut self, server_id: usize, latency: Duration) {
+        self.inner.record_latency(server_id, latency);
+    }
+
+    fn release_connection(&mut self, server_id: usize) {
+        self.inner.release_connection(server_id);
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.inner.add_server(server);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        self.inner.remove_server(server_id);
+        self.recent.retain(|&id| id != server_id);
+    }
+
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        self.inner.server(server_id)
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        self.inner.servers()
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.inner.healthy_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LeastConnections, RoundRobin};
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    fn selected_id(result: LoadBalancerResult) -> usize {
+        match result {
+            LoadBalancerResult::Selected { id } => id,
+            LoadBalancerResult::NoHealthyServers
+            | LoadBalancerResult::AllServersBusy
+            | LoadBalancerResult::Overloaded => panic!("expected a selection"),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_window_panics() {
+        let _ = AntiAffinity::new(LeastConnections::new(servers(3)), 0);
+    }
+
+    #[test]
+    fn test_no_repeat_within_window() {
+        let mut lb = AntiAffinity::new(LeastConnections::new(servers(3)), 2);
+        assert_eq!(lb.count(), 3);
+
+        for _ in 0..6 {
+            lb.select_server();
+        }
+        lb.unhealthy_server(0);
+        lb.healthy_server(0);
+
+        let mut selections = Vec::new();
+        for _ in 0..30 {
+            selections.push(selected_id(lb.select_server()));
+        }
+
+        for window in selections.windows(3) {
+            assert_ne!(window[0], window[1]);
+            assert_ne!(window[0], window[2]);
+            assert_ne!(window[1], window[2]);
+        }
+    }
+
+    #[test]
+    fn test_repeats_when_no_alternative() {
+        let mut lb = AntiAffinity::new(LeastConnections::new(servers(3)), 2);
+        lb.unhealthy_server(1);
+        lb.unhealthy_server(2);
+
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        lb.unhealthy_server(0);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    fn test_remove_server_drops_it_from_recent() {
+        let mut lb = AntiAffinity::new(RoundRobin::new(servers(3)), 2);
+        assert_eq!(selected_id(lb.select_server()), 0);
+        assert_eq!(selected_id(lb.select_server()), 1);
+        lb.remove_server(0);
+        assert_eq!(lb.count(), 2);
+        assert_eq!(lb.recent, VecDeque::from([1]));
+        assert_eq!(selected_id(lb.select_server()), 2);
+    }
+
+    #[test]
+    fn test_select_excluding_passes_exclusions_to_inner() {
+        let mut lb = AntiAffinity::new(LeastConnections::new(servers(3)), 1);
+        for _ in 0..10 {
+            assert_ne!(selected_id(lb.select_excluding(&[0])), 0);
+        }
+        assert_eq!(lb.inner().connections(0), 0);
+        assert_eq!(lb.inner().connections(1), 5);
+        assert_eq!(lb.inner().connections(2), 5);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::lb::Slots;
+use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+
+/// Least-connections that refuses a selection which would put the chosen server
+/// more than `max_skew` connections above the least-busy server.
+///
+/// The least-busy server is taken over the whole pool, unhealthy servers included,
+/// so a partial outage caps how much load is shifted onto the survivors.
+pub struct BoundedSkewLC<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
+    connection_counts: Vec<usize>,
+    max_skew: usize,
+}
+
+impl<T> BoundedSkewLC<T> {
+    pub fn new(servers: Vec<Server<T>>, max_skew: usize) -> Self {
+        assert!(!servers.is_empty());
+        assert!(max_skew > 0);
+        let connection_counts = vec![0; servers.len()];
+
+        Self {
+            slots: Slots::new(&servers),
+            servers,
+            connection_counts,
+            max_skew,
+        }
+    }
+
+    fn select(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        assert!(!self.servers.is_empty());
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        let mut best_server = None;
+        let mut min_connections = usize::MAX;
+
+        for (i, server) in self.servers.iter().enumerate() {
+            if server.state != ServerState::Healthy || exclude.contains(&server.id) {
+                continue;
+            }
+            if self.connection_counts[i] < min_connections {
+                min_connections = self.connection_counts[i];
+                best_server = Some(i);
+            }
+        }
+
+        match best_server {
+            Some(slot) => {
+                let least_busy = *self.connection_counts.iter().min().unwrap();
+                assert!(least_busy <= self.connection_counts[slot]);
+
+                if self.connection_counts[slot] + 1 - least_busy > self.max_skew {
+                    return LoadBalancerResult::AllServersBusy;
+                }
+                self.connection_counts[slot] += 1;
+                LoadBalancerResult::Selected {
+                    id: self.servers[slot].id,
+                }
+            }
+            None => LoadBalancerResult::NoHealthyServers,
+        }
+    }
+}
+
+impl<T> LoadBalancer<T> for BoundedSkewLC<T> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        self.select(&[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        self.select(exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.servers[slot].state = ServerState::Healthy;
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.servers[slot].state = ServerState::Unhealthy;
+        self.connection_counts[slot] = 0;
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Draining;
+    }
+
+    fn release_connection(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.connection_counts[slot] = self.connection_counts[slot].saturating_sub(1);
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        for (slot, &state) in states.iter().enumerate() {
+            if state == ServerState::Unhealthy {
+                self.connection_counts[slot] = 0;
+            }
+            self.servers[slot].state = state;
+        }
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.slots.push(server.id, self.servers.len());
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.servers.push(server);
+        self.connection_counts.push(0);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        let slot = self.slots.remove(server_id);
+        self.servers.remove(slot);
+        self.connection_counts.remove(slot);
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_empty_servers_panics() {
+        let _: BoundedSkewLC = BoundedSkewLC::new(vec![], 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_zero_skew_panics() {
+        let _ = BoundedSkewLC::new(servers(2), 0);
+    }
+
+    #[test]
+    fn test_all_healthy_never_busy() {
+        let mut lb = BoundedSkewLC::new(servers(3), 1);
+        assert_eq!(lb.count(), 3);
+
+        for i in 0..30 {
+            assert_eq!(
+                lb.select_server(),
+                LoadBalancerResult::Selected { id: i % 3 }
+            );
+        }
+    }
+
+    #[test]
+    fn test_skew_limit_returns_busy() {
+        let mut lb = BoundedSkewLC::new(servers(2), 2);
+        lb.unhealthy_server(1);
+
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::AllServersBusy);
+        assert_eq!(lb.select_server(), LoadBalancerResult::AllServersBusy);
+
+        lb.healthy_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        lb.unhealthy_server(0);
+        lb.unhealthy_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    fn test_select_excluding_skips_least_loaded() {
+        let mut lb = BoundedSkewLC::new(servers(3), 2);
+        let selected: Vec<_> = (0..4).map(|_| lb.select_excluding(&[0])).collect();
+        assert_eq!(
+            selected,
+            [1, 2, 1, 2].map(|id| LoadBalancerResult::Selected { id })
+        );
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::AllServersBusy
+        );
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(
+            lb.select_excluding(&[0, 1, 2]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+
+    #[test]
+    fn test_release_connection_frees_skew() {
+        let mut lb = BoundedSkewLC::new(servers(2), 1);
+        lb.unhealthy_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::AllServersBusy);
+
+        lb.release_connection(0);
+        lb.release_connection(0);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+    }
+}
@@ -0,0 +1,312 @@
+use crate::lb::{hash_key, Slots};
+use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Maps each request key to a server through a hash ring, so the same key keeps
+/// landing on the same server.
+///
+/// Every server owns `virtual_nodes` points on the ring. A key goes to the owner of
+/// the first point at or after its hash, skipping points of unhealthy servers, so
+/// a server going unhealthy, being added or being removed only remaps the keys
+/// it owned or takes over.
+pub struct ConsistentHashing<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
+    // (point, server id), sorted by point.
+    ring: Vec<(u64, usize)>,
+    virtual_nodes: usize,
+    // Servers out of rotation, draining ones included.
+    unhealthy_count: usize,
+    // Keys `select_server` hashes in place of a request key.
+    unkeyed_requests: u64,
+}
+
+impl<T> ConsistentHashing<T> {
+    pub fn new(servers: Vec<Server<T>>, virtual_nodes: usize) -> Self {
+        assert!(!servers.is_empty());
+        assert!(virtual_nodes > 0);
+        let unhealthy_count = servers
+            .iter()
+            .filter(|s| s.state != ServerState::Healthy)
+            .count();
+
+        let mut ring: Vec<(u64, usize)> = servers
+            .iter()
+            .flat_map(|server| {
+                points(server.id, virtual_nodes).map(move |point| (point, server.id))
+            })
+            .collect();
+        ring.sort_unstable();
+
+        Self {
+            slots: Slots::new(&servers),
+            servers,
+            ring,
+            virtual_nodes,
+            unhealthy_count,
+            unkeyed_requests: 0,
+        }
+    }
+
+    /// The next running-counter key `select_server` hashes in place of a request key.
+    fn unkeyed_key(&mut self) -> [u8; 8] {
+        let key = self.unkeyed_requests.to_le_bytes();
+        self.unkeyed_requests = self.unkeyed_requests.wrapping_add(1);
+        key
+    }
+
+    /// Selects for `key`, skipping servers in `exclude`.
+    fn select(&self, key: &[u8], exclude: &[usize]) -> LoadBalancerResult {
+        assert!(!self.servers.is_empty());
+        assert!(!self.ring.is_empty());
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.unhealthy_count == self.servers.len() {
+            return LoadBalancerResult::NoHealthyServers;
+        }
+
+        let hash = hash_key(key);
+        let start = self.ring.partition_point(|&(point, _)| point < hash);
+        for offset in 0..self.ring.len() {
+            let (_, server_id) = self.ring[(start + offset) % self.ring.len()];
+            let healthy = self.servers[self.slots.get(server_id)].state == ServerState::Healthy;
+            if healthy && !exclude.contains(&server_id) {
+                return LoadBalancerResult::Selected { id: server_id };
+            }
+        }
+        assert!(
+            !exclude.is_empty(),
+            "a healthy server owns a point on the ring"
+        );
+        LoadBalancerResult::NoHealthyServers
+    }
+}
+
+fn points(server_id: usize, virtual_nodes: usize) -> impl Iterator<Item = u64> {
+    (0..virtual_nodes).map(move |node| {
+        let mut hasher = DefaultHasher::new();
+        (server_id, node).hash(&mut hasher);
+        hasher.finish()
+    })
+}
+
+impl<T> LoadBalancer<T> for ConsistentHashing<T> {
+    /// Without a key, successive calls are spread over the ring by hashing a
+    /// running counter.
+    fn select_server(&mut self) -> LoadBalancerResult {
+        let key = self.unkeyed_key();
+        self.select(&key, &[])
+    }
+
+    fn select_for(&mut self, key: &[u8]) -> LoadBalancerResult {
+        self.select(key, &[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        let key = self.unkeyed_key();
+        self.select(&key, exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.servers[slot].state != ServerState::Healthy {
+            self.unhealthy_count -= 1;
+            self.servers[slot].state = ServerState::Healthy;
+        }
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.servers[slot].state == ServerState::Healthy {
+            self.unhealthy_count += 1;
+        }
+        self.servers[slot].state = ServerState::Unhealthy;
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.servers[slot].state == ServerState::Healthy {
+            self.unhealthy_count += 1;
+        }
+        self.servers[slot].state = ServerState::Draining;
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+
+        for (server, &state) in self.servers.iter_mut().zip(states) {
+            server.state = state;
+        }
+        self.unhealthy_count = self
+            .servers
+            .iter()
+            .filter(|s| s.state != ServerState::Healthy)
+            .count();
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        let server_id = server.id;
+        self.slots.push(server_id, self.servers.len());
+        if server.state != ServerState::Healthy {
+            self.unhealthy_count += 1;
+        }
+        self.servers.push(server);
+        self.ring
+            .extend(points(server_id, self.virtual_nodes).map(|point| (point, server_id)));
+        self.ring.sort_unstable();
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+
+        let slot = self.slots.remove(server_id);
+        if self.servers.remove(slot).state != ServerState::Healthy {
+            self.unhealthy_count -= 1;
+        }
+        self.ring.retain(|&(_, id)| id != server_id);
+        assert!(self.ring.len() == self.servers.len() * self.virtual_nodes);
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
+
+    fn healthy_count(&self) -> usize {
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        self.servers.len() - self.unhealthy_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    fn keys(count: usize) -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|i| format!("user-{i}").into_bytes())
+            .collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_empty_servers_panics() {
+        let _: ConsistentHashing = ConsistentHashing::new(vec![], 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_zero_virtual_nodes_panics() {
+        let _ = ConsistentHashing::new(servers(3), 0);
+    }
+
+    #[test]
+    fn test_same_key_selects_same_server() {
+        let mut lb = ConsistentHashing::new(servers(5), 50);
+        for key in keys(100) {
+            let first = lb.select_for(&key);
+            assert!(matches!(first, LoadBalancerResult::Selected { .. }));
+            for _ in 0..10 {
+                let _ = lb.select_server();
+                assert_eq!(lb.select_for(&key), first);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unhealthy_server_only_remaps_its_keys() {
+        let mut lb = ConsistentHashing::new(servers(5), 50);
+        let keys = keys(1000);
+        let before: Vec<_> = keys.iter().map(|key| lb.select_for(key)).collect();
+        let owned = before
+            .iter()
+            .filter(|result| **result == LoadBalancerResult::Selected { id: 2 })
+            .count();
+        assert!(owned > 0);
+        assert!(owned < keys.len() / 2);
+
+        lb.unhealthy_server(2);
+        for (key, before) in keys.iter().zip(&before) {
+            let after = lb.select_for(key);
+            assert_ne!(after, LoadBalancerResult::Selected { id: 2 });
+            if *before != (LoadBalancerResult::Selected { id: 2 }) {
+                assert_eq!(after, *before);
+            }
+        }
+
+        lb.healthy_server(2);
+        let restored: Vec<_> = keys.iter().map(|key| lb.select_for(key)).collect();
+        assert_eq!(restored, before);
+    }
+
+    #[test]
+    fn test_all_unhealthy_returns_no_healthy_servers() {
+        let mut lb = ConsistentHashing::new(servers(2), 10);
+        lb.unhealthy_server(0);
+        lb.unhealthy_server(1);
+        assert_eq!(lb.select_for(b"key"), LoadBalancerResult::NoHealthyServers);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+        assert!(!lb.has_quorum(1));
+    }
+
+    #[test]
+    fn test_added_server_only_takes_over_keys() {
+        let mut lb = ConsistentHashing::new(servers(4), 50);
+        let keys = keys(1000);
+        let before: Vec<_> = keys.iter().map(|key| lb.select_for(key)).collect();
+
+        lb.add_server(Server::new(4, ServerState::Healthy));
+        let mut moved = 0;
+        for (key, before) in keys.iter().zip(&before) {
+            let after = lb.select_for(key);
+            if after != *before {
+                assert_eq!(after, LoadBalancerResult::Selected { id: 4 });
+                moved += 1;
+            }
+        }
+        assert!(moved > 0);
+        assert!(moved < keys.len() / 2);
+
+        lb.remove_server(4);
+        let restored: Vec<_> = keys.iter().map(|key| lb.select_for(key)).collect();
+        assert_eq!(restored, before);
+    }
+
+    #[test]
+    fn test_select_excluding_always_avoids_excluded() {
+        let mut lb = ConsistentHashing::new(servers(2), 50);
+        for _ in 0..100 {
+            assert_eq!(
+                lb.select_excluding(&[0]),
+                LoadBalancerResult::Selected { id: 1 }
+            );
+        }
+        lb.unhealthy_server(1);
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::lb::{hash_key, Slots};
+use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+
+/// Pins each key, typically a client IP, to the server listed at its hash modulo
+/// the server count.
+///
+/// When that server is unhealthy the key probes the following servers in order
+/// and takes the first healthy one, so a client only moves while its server is
+/// down and returns once it recovers. Adding or removing a server changes the
+/// modulus and remaps most keys; `ConsistentHashing` avoids that.
+pub struct IpHash<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
+    // Servers out of rotation, draining ones included.
+    unhealthy_count: usize,
+    // Keys `select_server` hashes in place of a request key.
+    unkeyed_requests: u64,
+}
+
+impl<T> IpHash<T> {
+    pub fn new(servers: Vec<Server<T>>) -> Self {
+        assert!(!servers.is_empty());
+        let unhealthy_count = servers
+            .iter()
+            .filter(|s| s.state != ServerState::Healthy)
+            .count();
+
+        Self {
+            slots: Slots::new(&servers),
+            servers,
+            unhealthy_count,
+            unkeyed_requests: 0,
+        }
+    }
+
+    /// The next running-counter key `select_server` hashes in place of a request key.
+    fn unkeyed_key(&mut self) -> [u8; 8] {
+        let key = self.unkeyed_requests.to_le_bytes();
+        self.unkeyed_requests = self.unkeyed_requests.wrapping_add(1);
+        key
+    }
+
+    /// Selects for `key`, skipping servers in `exclude`.
+    fn select(&self, key: &[u8], exclude: &[usize]) -> LoadBalancerResult {
+        assert!(!self.servers.is_empty());
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.unhealthy_count == self.servers.len() {
+            return LoadBalancerResult::NoHealthyServers;
+        }
+
+        let start = (hash_key(key) % self.servers.len() as u64) as usize;
+        for offset in 0..self.servers.len() {
+            let server = &self.servers[(start + offset) % self.servers.len()];
+            if server.state == ServerState::Healthy && !exclude.contains(&server.id) {
+                return LoadBalancerResult::Selected { id: server.id };
+            }
+        }
+        assert!(
+            !exclude.is_empty(),
+            "unhealthy_count says a server is healthy"
+        );
+        LoadBalancerResult::NoHealthyServers
+    }
+}
+
+impl<T> LoadBalancer<T> for IpHash<T> {
+    /// Without a key, successive calls are spread by hashing a running counter.
+    fn select_server(&mut self) -> LoadBalancerResult {
+        let key = self.unkeyed_key();
+        self.select(&key, &[])
+    }
+
+    fn select_for(&mut self, key: &[u8]) -> LoadBalancerResult {
+        self.select(key, &[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        let key = self.unkeyed_key();
+        self.select(&key, exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.servers[slot].state != ServerState::Healthy {
+            self.unhealthy_count -= 1;
+            self.servers[slot].state = ServerState::Healthy;
+        }
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.servers[slot].state == ServerState::Healthy {
+            self.unhealthy_count += 1;
+        }
+        self.servers[slot].state = ServerState::Unhealthy;
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.servers[slot].state == ServerState::Healthy {
+            self.unhealthy_count += 1;
+        }
+        self.servers[slot].state = ServerState::Draining;
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+
+        for (server, &state) in self.servers.iter_mut().zip(states) {
+            server.state = state;
+        }
+        self.unhealthy_count = self
+            .servers
+            .iter()
+            .filter(|s| s.state != ServerState::Healthy)
+            .count();
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.slots.push(server.id, self.servers.len());
+        if server.state != ServerState::Healthy {
+            self.unhealthy_count += 1;
+        }
+        self.servers.push(server);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+
+        let slot = self.slots.remove(server_id);
+        if self.servers.remove(slot).state != ServerState::Healthy {
+            self.unhealthy_count -= 1;
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
+
+    fn healthy_count(&self) -> usize {
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        self.servers.len() - self.unhealthy_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    fn selected_id(result: LoadBalancerResult) -> usize {
+        match result {
+            LoadBalancerResult::Selected { id } => id,
+            other => panic!("expected a selection, got {other:?}"),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_empty_servers_panics() {
+        let _: IpHash = IpHash::new(vec![]);
+    }
+
+    #[test]
+    fn test_same_key_selects_same_server() {
+        let mut lb = IpHash::new(servers(4));
+        let key = [10, 0, 0, 7];
+        let first = lb.select_for(&key);
+        for _ in 0..20 {
+            let _ = lb.select_server();
+            assert_eq!(lb.select_for(&key), first);
+        }
+    }
+
+    #[test]
+    fn test_rehashes_only_while_server_unhealthy() {
+        let mut lb = IpHash::new(servers(4));
+        let key = [192, 168, 1, 20];
+        let pinned = selected_id(lb.select_for(&key));
+
+        lb.unhealthy_server((pinned + 2) % 4);
+        assert_eq!(selected_id(lb.select_for(&key)), pinned);
+
+        lb.unhealthy_server(pinned);
+        let fallback = selected_id(lb.select_for(&key));
+        assert_ne!(fallback, pinned);
+        assert_eq!(selected_id(lb.select_for(&key)), fallback);
+
+        lb.healthy_server(pinned);
+        assert_eq!(selected_id(lb.select_for(&key)), pinned);
+    }
+
+    #[test]
+    fn test_all_unhealthy_returns_no_healthy_servers() {
+        let mut lb = IpHash::new(servers(2));
+        lb.unhealthy_server(0);
+        lb.unhealthy_server(1);
+        assert_eq!(lb.select_for(b"key"), LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    fn test_select_excluding_always_avoids_excluded() {
+        let mut lb = IpHash::new(servers(2));
+        for _ in 0..100 {
+            assert_eq!(
+                lb.select_excluding(&[0]),
+                LoadBalancerResult::Selected { id: 1 }
+            );
+        }
+        lb.unhealthy_server(1);
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+}
@@ -1,30 +1,294 @@
 //! Load Balancer core types and trait
 
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{DefaultHasher, Hasher};
+use std::time::Duration;
+
 /// Server
 #[derive(Debug, Clone)]
-pub struct Server {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Server<T = ()> {
     pub id: usize,
     pub state: ServerState,
+    /// Relative capacity for weighted balancers; the others ignore it.
+    pub weight: u32,
+    /// Failover tier for tiered balancers, lower tiers first; the others ignore it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub priority: u32,
+    /// Caller data such as a hostname or zone; balancers never look at it.
+    pub metadata: T,
+}
+
+impl Server {
+    /// A server of weight 1.
+    pub fn new(id: usize, state: ServerState) -> Self {
+        Self::with_weight(id, state, 1)
+    }
+
+    pub fn with_weight(id: usize, state: ServerState, weight: u32) -> Self {
+        assert!(weight > 0);
+
+        Server {
+            id,
+            state,
+            weight,
+            priority: 0,
+            metadata: (),
+        }
+    }
+}
+
+impl<T> Server<T> {
+    /// A server of weight 1 carrying `metadata`.
+    pub fn with_metadata(id: usize, state: ServerState, metadata: T) -> Self {
+        Server {
+            id,
+            state,
+            weight: 1,
+            priority: 0,
+            metadata,
+        }
+    }
+
+    /// The same server placed in failover tier `priority`.
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
 }
 
 /// Server health states
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ServerState {
     Healthy,
     Unhealthy,
+    /// Out of rotation for new requests while existing connections finish.
+    Draining,
 }
 
 /// Load balancer result
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LoadBalancerResult {
-    Selected { id: usize },
+    Selected {
+        id: usize,
+    },
     NoHealthyServers,
+    AllServersBusy,
+    /// Some server is healthy, but every healthy server is at its connection limit.
+    Overloaded,
 }
 
-/// Load Balancer trait
-pub trait LoadBalancer {
+/// A server list a balancer constructor would have rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    EmptyServerList,
+    DuplicateServerId(usize),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::EmptyServerList => f.write_str("server list must not be empty"),
+            ConfigError::DuplicateServerId(id) => write!(f, "server id {id} is used twice"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Selection counters for a balancer. `per_server[i]` counts selections of the
+/// i-th server in the order servers were added, as `servers()` lists them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LbMetrics {
+    pub total_selections: u64,
+    pub per_server: Vec<u64>,
+    pub no_healthy: u64,
+}
+
+impl LbMetrics {
+    pub(crate) fn new(server_count: usize) -> Self {
+        LbMetrics {
+            per_server: vec![0; server_count],
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn record(&mut self, result: &LoadBalancerResult, slots: &Slots) {
+        match result {
+            LoadBalancerResult::Selected { id } => {
+                let slot = slots.get(*id);
+                assert!(slot < self.per_server.len());
+                self.total_selections += 1;
+                self.per_server[slot] += 1;
+            }
+            LoadBalancerResult::NoHealthyServers => self.no_healthy += 1,
+            LoadBalancerResult::AllServersBusy | LoadBalancerResult::Overloaded => {}
+        }
+    }
+}
+
+/// Callback told the outcome of each selection.
+pub(crate) type SelectHook = Box<dyn FnMut(&LoadBalancerResult) + Send>;
+
+/// Hash of a request key, shared by the balancers that select by key.
+pub(crate) fn hash_key(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(key);
+    hasher.finish()
+}
+
+/// Position of each server in a balancer's server list, keyed by `Server.id`, so
+/// ids stay put while servers are added and removed.
+#[derive(Debug)]
+pub(crate) struct Slots {
+    slots: HashMap<usize, usize>,
+}
+
+impl Slots {
+    pub(crate) fn new<T>(servers: &[Server<T>]) -> Self {
+        Self::try_new(servers).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like `new`, but reports a repeated id instead of panicking.
+    pub(crate) fn try_new<T>(servers: &[Server<T>]) -> Result<Self, ConfigError> {
+        let mut slots = HashMap::with_capacity(servers.len());
+        for (slot, server) in servers.iter().enumerate() {
+            if slots.insert(server.id, slot).is_some() {
+                return Err(ConfigError::DuplicateServerId(server.id));
+            }
+        }
+
+        Ok(Slots { slots })
+    }
+
+    /// Slot of `server_id`. Panics on an unknown id.
+    pub(crate) fn get(&self, server_id: usize) -> usize {
+        match self.slots.get(&server_id) {
+            Some(&slot) => slot,
+            None => panic!("unknown server id {server_id}"),
+        }
+    }
+
+    /// Records `server_id` as appended at `slot`.
+    pub(crate) fn push(&mut self, server_id: usize, slot: usize) {
+        assert!(slot == self.slots.len());
+
+        let previous = self.slots.insert(server_id, slot);
+        assert!(previous.is_none(), "server ids must be unique");
+    }
+
+    /// Forgets `server_id` and returns its slot. Later servers move down one slot,
+    /// matching a `Vec::remove` of that slot.
+    pub(crate) fn remove(&mut self, server_id: usize) -> usize {
+        let slot = self.get(server_id);
+        self.slots.remove(&server_id);
+        for other in self.slots.values_mut() {
+            if *other > slot {
+                *other -= 1;
+            }
+        }
+        slot
+    }
+}
+
+/// Load Balancer trait, over servers carrying metadata of type `T`.
+///
+/// Servers are addressed by `Server.id`, which must be unique within a balancer
+/// and stays the same as other servers are added and removed. `Selected` reports
+/// that id too.
+pub trait LoadBalancer<T = ()> {
     fn select_server(&mut self) -> LoadBalancerResult;
+    /// Selects a server for a request identified by `key`. Balancers that ignore
+    /// request identity fall back to `select_server`.
+    fn select_for(&mut self, _key: &[u8]) -> LoadBalancerResult {
+        self.select_server()
+    }
+    /// Selects a server whose id is not in `exclude`, such as one not tried yet
+    /// for this request, or `NoHealthyServers` when every healthy server is
+    /// excluded. Every balancer in this crate skips `exclude` in its own scan; the
+    /// default asks `select_server` again while it returns an excluded server, at
+    /// most once per server.
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        let mut result = self.select_server();
+        for _ in 1..self.count() {
+            match result {
+                LoadBalancerResult::Selected { id } if exclude.contains(&id) => {
+                    result = self.select_server();
+                }
+                _ => break,
+            }
+        }
+        match result {
+            LoadBalancerResult::Selected { id } if exclude.contains(&id) => {
+                LoadBalancerResult::NoHealthyServers
+            }
+            other => other,
+        }
+    }
     fn healthy_server(&mut self, server_id: usize);
+    /// Reports how long a request to `server_id` took. Balancers that do not
+    /// select by latency ignore it.
+    fn record_latency(&mut self, _server_id: usize, _latency: Duration) {}
+    /// Signals that a connection to `server_id` finished, or that a selection was
+    /// never used, saturating at zero. Balancers that do not count connections
+    /// ignore it.
+    fn release_connection(&mut self, _server_id: usize) {}
+    /// Adds `server` under its own id. Panics if that id is already taken.
+    fn add_server(&mut self, server: Server<T>);
+    /// Removes the server with `server_id`. Panics when asked to remove the only
+    /// server, as a balancer never runs empty.
+    fn remove_server(&mut self, server_id: usize);
     fn unhealthy_server(&mut self, server_id: usize);
+    /// Stops selecting `server_id` without dropping its existing connections, for
+    /// a graceful shutdown. `healthy_server` puts it back in rotation.
+    fn drain_server(&mut self, server_id: usize);
+    /// Applies a health snapshot of the whole pool at once, `states[i]` going to
+    /// the i-th server in the order servers were added. Each server ends up as if
+    /// marked individually. Panics unless there is one state per server.
+    fn set_states(&mut self, states: &[ServerState]);
     fn count(&self) -> usize;
+    /// The server behind `server_id`, e.g. to read the metadata of a selection.
+    /// Panics on an unknown id.
+    fn server(&self, server_id: usize) -> &Server<T>;
+    /// Every server in the order it was added, the order `set_states` uses.
+    fn servers(&self) -> &[Server<T>];
+    /// Health of `server_id` as last marked. Panics on an unknown id.
+    fn server_state(&self, server_id: usize) -> ServerState {
+        self.server(server_id).state
+    }
+    fn healthy_count(&self) -> usize;
+    /// Whether at least `quorum` servers are healthy.
+    fn has_quorum(&self, quorum: usize) -> bool {
+        self.healthy_count() >= quorum
+    }
+    /// Selects a server and calls `attempt` with its id; when the attempt fails,
+    /// moves on to a server not tried yet through `select_excluding`, for at most
+    /// `max_attempts` attempts.
+    /// Returns the server whose attempt succeeded, `NoHealthyServers` once the
+    /// attempts or the untried servers run out, or whatever non-selection the
+    /// strategy reported.
+    fn select_with_retry<F>(&mut self, max_attempts: usize, mut attempt: F) -> LoadBalancerResult
+    where
+        F: FnMut(usize) -> bool,
+        Self: Sized,
+    {
+        assert!(max_attempts > 0);
+
+        let mut tried: Vec<usize> = Vec::with_capacity(max_attempts);
+        while tried.len() < max_attempts {
+            let id = match self.select_excluding(&tried) {
+                LoadBalancerResult::Selected { id } => id,
+                other => return other,
+            };
+            assert!(!tried.contains(&id));
+            if attempt(id) {
+                return LoadBalancerResult::Selected { id };
+            }
+            tried.push(id);
+        }
+        LoadBalancerResult::NoHealthyServers
+    }
 }
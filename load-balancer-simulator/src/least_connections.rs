@@ -1,117 +1,349 @@
-use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
-
-pub struct LeastConnections {
-    servers: Vec<Server>,
+use crate::lb::{SelectHook, Slots};
+use crate::{
+    ConfigError, LbMetrics, LoadBalancer, LoadBalancerResult, RandomSource, Server, ServerState,
+};
+
+/// Selects the healthy server with the fewest connections. Ties go to the server
+/// listed first, or to a random one among them with `random_tie_break`.
+pub struct LeastConnections<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
     connection_counts: Vec<usize>,
+    max_connections: Vec<Option<usize>>,
+    on_connection_change: Option<Box<dyn FnMut(usize, usize) + Send>>,
+    on_select: Option<SelectHook>,
+    metrics: LbMetrics,
+    tie_break: Option<Box<dyn RandomSource + Send>>,
+}
+
+/// What `LeastConnections` needs to carry on selecting where it left off,
+/// including the connection counts. Callbacks and metrics are not part of it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LeastConnectionsSnapshot<T = ()> {
+    pub servers: Vec<Server<T>>,
+    pub connection_counts: Vec<usize>,
+    pub max_connections: Vec<Option<usize>>,
 }
 
-impl LeastConnections {
-    pub fn new(servers: Vec<Server>) -> Self {
-        assert!(!servers.is_empty());
+impl<T> LeastConnections<T> {
+    /// Panics on a server list `try_new` would reject.
+    pub fn new(servers: Vec<Server<T>>) -> Self {
+        Self::try_new(servers).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    pub fn try_new(servers: Vec<Server<T>>) -> Result<Self, ConfigError> {
+        if servers.is_empty() {
+            return Err(ConfigError::EmptyServerList);
+        }
         let connection_counts = vec![0; servers.len()];
+        let max_connections = vec![None; servers.len()];
 
-        Self {
+        Ok(Self {
+            slots: Slots::try_new(&servers)?,
+            metrics: LbMetrics::new(servers.len()),
             servers,
             connection_counts,
+            max_connections,
+            on_connection_change: None,
+            on_select: None,
+            tie_break: None,
+        })
+    }
+
+    /// Calls `f(server_id, new_count)` whenever a server's connection count changes,
+    /// whether by selection, release, reset or the server turning unhealthy.
+    pub fn on_connection_change(mut self, f: impl FnMut(usize, usize) + Send + 'static) -> Self {
+        self.on_connection_change = Some(Box::new(f));
+        self
+    }
+
+    /// Breaks ties between equally loaded servers with `source` instead of taking
+    /// the one listed first, so a pool sitting at equal counts does not pile onto
+    /// its first server.
+    pub fn random_tie_break(mut self, source: impl RandomSource + Send + 'static) -> Self {
+        self.tie_break = Some(Box::new(source));
+        self
+    }
+
+    /// Calls `f` with the outcome of every `select_server`, including
+    /// `NoHealthyServers` and `Overloaded`.
+    pub fn on_select(mut self, f: impl FnMut(&LoadBalancerResult) + Send + 'static) -> Self {
+        self.on_select = Some(Box::new(f));
+        self
+    }
+
+    pub fn metrics(&self) -> &LbMetrics {
+        &self.metrics
+    }
+
+    /// Resumes from `snapshot`. Panics if its fields disagree with each other.
+    pub fn from_snapshot(snapshot: LeastConnectionsSnapshot<T>) -> Self {
+        let mut lb = Self::new(snapshot.servers);
+        assert!(snapshot.connection_counts.len() == lb.servers.len());
+        assert!(snapshot.max_connections.len() == lb.servers.len());
+        assert!(snapshot.max_connections.iter().all(|&max| max != Some(0)));
+
+        lb.connection_counts = snapshot.connection_counts;
+        lb.max_connections = snapshot.max_connections;
+        lb
+    }
+
+    fn set_connections(&mut self, slot: usize, count: usize) {
+        assert!(slot < self.connection_counts.len());
+
+        if self.connection_counts[slot] == count {
+            return;
+        }
+        self.connection_counts[slot] = count;
+        if let Some(on_connection_change) = &mut self.on_connection_change {
+            on_connection_change(self.servers[slot].id, count);
         }
     }
+
+    /// Caps the connections `server_id` may hold; a server at its cap is skipped
+    /// however lightly loaded. `None` removes the cap. Lowering the cap below the
+    /// current count drops no connections.
+    pub fn set_max_connections(&mut self, server_id: usize, max_connections: Option<usize>) {
+        let slot = self.slots.get(server_id);
+        assert!(self.max_connections.len() == self.servers.len());
+        assert!(max_connections != Some(0));
+
+        self.max_connections[slot] = max_connections;
+    }
+
+    /// Zeroes the connection count of `server_id` without touching its health,
+    /// for correcting counts that drifted from reality.
+    pub fn reset_connections(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.set_connections(slot, 0);
+    }
+
+    /// Releases one connection from each listed server, saturating at zero. An id
+    /// listed twice releases two connections.
+    pub fn release_connections(&mut self, server_ids: &[usize]) {
+        for &server_id in server_ids {
+            self.release_connection(server_id);
+        }
+    }
+
+    /// Connections currently counted against `server_id`.
+    pub fn connections(&self, server_id: usize) -> usize {
+        let slot = self.slots.get(server_id);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.connection_counts[slot]
+    }
+
+    /// `(id, connection_count)` for every server, busiest first and ties by id.
+    /// Unhealthy servers are included; marking a server unhealthy zeroes its count,
+    /// so they sort last.
+    pub fn servers_by_load(&self) -> Vec<(usize, usize)> {
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        let mut servers_by_load: Vec<(usize, usize)> = self
+            .servers
+            .iter()
+            .map(|server| server.id)
+            .zip(self.connection_counts.iter().copied())
+            .collect();
+        servers_by_load.sort_by_key(|&(id, connections)| (std::cmp::Reverse(connections), id));
+        servers_by_load
+    }
 }
 
-impl LoadBalancer for LeastConnections {
-    fn select_server(&mut self) -> LoadBalancerResult {
+impl<T: Clone> LeastConnections<T> {
+    pub fn snapshot(&self) -> LeastConnectionsSnapshot<T> {
+        LeastConnectionsSnapshot {
+            servers: self.servers.clone(),
+            connection_counts: self.connection_counts.clone(),
+            max_connections: self.max_connections.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::de::DeserializeOwned> LeastConnections<T> {
+    /// A balancer over the JSON array of servers in `json`. Panics if the array is
+    /// empty, like `new`.
+    pub fn from_servers_json(json: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self::new(serde_json::from_str(json)?))
+    }
+}
+
+impl<T> LeastConnections<T> {
+    fn select(&mut self, exclude: &[usize]) -> LoadBalancerResult {
         assert!(!self.servers.is_empty());
         assert!(self.connection_counts.len() == self.servers.len());
+        assert!(self.max_connections.len() == self.servers.len());
 
-        let mut best_server = None;
+        // Every eligible slot at the lowest count seen so far, in list order.
+        let mut least_loaded: Vec<usize> = Vec::new();
         let mut min_connections = usize::MAX;
+        let mut saturated = false;
 
         for (i, server) in self.servers.iter().enumerate() {
-            if server.state == ServerState::Healthy && self.connection_counts[i] < min_connections {
+            if server.state != ServerState::Healthy || exclude.contains(&server.id) {
+                continue;
+            }
+            if self.max_connections[i].is_some_and(|max| self.connection_counts[i] >= max) {
+                saturated = true;
+                continue;
+            }
+            if self.connection_counts[i] < min_connections {
                 min_connections = self.connection_counts[i];
-                best_server = Some(i);
+                least_loaded.clear();
+            }
+            if self.connection_counts[i] == min_connections {
+                least_loaded.push(i);
             }
         }
 
-        match best_server {
-            Some(server_id) => {
-                self.connection_counts[server_id] += 1;
-                LoadBalancerResult::Selected { id: server_id }
+        let best_server = match &mut self.tie_break {
+            Some(source) if least_loaded.len() > 1 => {
+                Some(least_loaded[source.next_index(least_loaded.len())])
+            }
+            _ => least_loaded.first().copied(),
+        };
+
+        let result = match best_server {
+            Some(slot) => {
+                self.set_connections(slot, self.connection_counts[slot] + 1);
+                LoadBalancerResult::Selected {
+                    id: self.servers[slot].id,
+                }
             }
+            None if saturated => LoadBalancerResult::Overloaded,
             None => LoadBalancerResult::NoHealthyServers,
+        };
+        self.metrics.record(&result, &self.slots);
+        if let Some(on_select) = &mut self.on_select {
+            on_select(&result);
         }
+        result
+    }
+}
+
+impl<T> LoadBalancer<T> for LeastConnections<T> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        self.select(&[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        self.select(exclude)
     }
 
     fn healthy_server(&mut self, server_id: usize) {
-        assert!(server_id < self.servers.len());
+        let slot = self.slots.get(server_id);
         assert!(self.connection_counts.len() == self.servers.len());
 
-        self.servers[server_id].state = ServerState::Healthy;
+        self.servers[slot].state = ServerState::Healthy;
     }
 
     fn unhealthy_server(&mut self, server_id: usize) {
-        assert!(server_id < self.servers.len());
+        let slot = self.slots.get(server_id);
         assert!(self.connection_counts.len() == self.servers.len());
 
-        self.servers[server_id].state = ServerState::Unhealthy;
-        self.connection_counts[server_id] = 0;
+        self.servers[slot].state = ServerState::Unhealthy;
+        self.set_connections(slot, 0);
     }
 
-    fn count(&self) -> usize {
-        self.servers.len()
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Draining;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::rngs::StdRng;
-    use rand::{Rng, SeedableRng};
+    /// Releasing after every call keeps the counts tracking active connections
+    /// rather than every selection.
+    fn release_connection(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.connection_counts.len() == self.servers.len());
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    enum Step {
-        SelectServer,
-        MarkHealthy(usize),
-        MarkUnhealthy(usize),
+        let count = self.connection_counts[slot].saturating_sub(1);
+        self.set_connections(slot, count);
     }
 
-    fn generate_random_steps(seed: u64, count: usize, server_count: usize) -> Vec<Step> {
-        let mut rng = StdRng::seed_from_u64(seed);
-        let mut steps = Vec::with_capacity(count);
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+        assert!(self.connection_counts.len() == self.servers.len());
 
-        for _ in 0..count {
-            let choice = rng.random_range(0..3);
-            let step = match choice {
-                0 => Step::SelectServer,
-                1 => Step::MarkHealthy(rng.random_range(0..server_count)),
-                _ => Step::MarkUnhealthy(rng.random_range(0..server_count)),
-            };
-            steps.push(step);
+        for (slot, &state) in states.iter().enumerate() {
+            if state == ServerState::Unhealthy {
+                self.set_connections(slot, 0);
+            }
+            self.servers[slot].state = state;
         }
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.slots.push(server.id, self.servers.len());
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.servers.push(server);
+        self.connection_counts.push(0);
+        self.max_connections.push(None);
+        self.metrics.per_server.push(0);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        let slot = self.slots.remove(server_id);
+        self.servers.remove(slot);
+        self.connection_counts.remove(slot);
+        self.max_connections.remove(slot);
+        self.metrics.per_server.remove(slot);
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
 
-        steps
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
     }
 
+    fn healthy_count(&self) -> usize {
+        self.servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim;
+    use crate::ScriptedRandom;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
     #[test]
     #[should_panic]
     fn test_new_empty_servers_panics() {
-        let _ = LeastConnections::new(vec![]);
+        let _: LeastConnections = LeastConnections::new(vec![]);
     }
 
     #[test]
     fn test_new_one_server() {
-        let lb = LeastConnections::new(vec![Server {
-            id: 0,
-            state: ServerState::Healthy,
-        }]);
+        let lb = LeastConnections::new(vec![Server::new(0, ServerState::Healthy)]);
         assert_eq!(lb.count(), 1);
     }
 
     #[test]
     fn test_select_server() {
-        let mut lb = LeastConnections::new(vec![Server {
-            id: 0,
-            state: ServerState::Healthy,
-        }]);
+        let mut lb = LeastConnections::new(vec![Server::new(0, ServerState::Healthy)]);
         assert_eq!(lb.count(), 1);
 
         assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
@@ -127,14 +359,8 @@ mod tests {
     #[test]
     fn test_least_connections_behavior() {
         let mut lb = LeastConnections::new(vec![
-            Server {
-                id: 0,
-                state: ServerState::Healthy,
-            },
-            Server {
-                id: 1,
-                state: ServerState::Healthy,
-            },
+            Server::new(0, ServerState::Healthy),
+            Server::new(1, ServerState::Healthy),
         ]);
         assert_eq!(lb.count(), 2);
 
@@ -148,6 +374,85 @@ mod tests {
         assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
     }
 
+    #[test]
+    fn test_reset_connections_keeps_health() {
+        let mut lb = LeastConnections::new(vec![
+            Server::new(0, ServerState::Healthy),
+            Server::new(1, ServerState::Healthy),
+        ]);
+
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+
+        lb.reset_connections(1);
+        assert_eq!(lb.connection_counts, vec![2, 0]);
+        assert_eq!(lb.servers[1].state, ServerState::Healthy);
+
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_reset_connections_out_of_range_panics() {
+        let mut lb = LeastConnections::new(vec![Server::new(0, ServerState::Healthy)]);
+        lb.reset_connections(1);
+    }
+
+    #[test]
+    fn test_has_quorum() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        assert!(lb.has_quorum(3));
+        assert!(!lb.has_quorum(4));
+
+        lb.unhealthy_server(0);
+        assert!(lb.has_quorum(2));
+        assert!(!lb.has_quorum(3));
+
+        lb.unhealthy_server(1);
+        lb.unhealthy_server(2);
+        assert!(lb.has_quorum(0));
+        assert!(!lb.has_quorum(1));
+    }
+
+    #[test]
+    fn test_release_connections_batch() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        for _ in 0..9 {
+            lb.select_server();
+        }
+        assert_eq!(lb.connection_counts, vec![3, 3, 3]);
+
+        lb.release_connections(&[0, 2, 2]);
+        assert_eq!(lb.connection_counts, vec![2, 3, 1]);
+
+        lb.release_connections(&[2, 2, 2]);
+        assert_eq!(lb.connection_counts, vec![2, 3, 0]);
+
+        lb.release_connections(&[]);
+        assert_eq!(lb.connection_counts, vec![2, 3, 0]);
+
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 2 });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_release_connections_out_of_range_panics() {
+        let mut lb = LeastConnections::new(vec![Server::new(0, ServerState::Healthy)]);
+        lb.release_connections(&[0, 1]);
+    }
+
     #[test]
     fn test_least_connections_random_sequence() {
         let server_count = 5;
@@ -155,29 +460,493 @@ mod tests {
         let count: usize = 100_000;
 
         let servers = (0..server_count)
-            .map(|id| Server {
-                id,
-                state: ServerState::Healthy,
-            })
+            .map(|id| Server::new(id, ServerState::Healthy))
             .collect();
 
         let mut lb = LeastConnections::new(servers);
-        let steps = generate_random_steps(seed, count, server_count);
+        let steps = sim::random_steps(seed, count, server_count);
+
+        let results = sim::run_checked(&mut lb, &steps);
+        let selections = steps
+            .iter()
+            .filter(|step| **step == sim::LbStep::SelectServer)
+            .count();
+        assert_eq!(results.len(), selections);
+    }
 
-        for step in steps {
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| match step {
-                Step::SelectServer => {
-                    lb.select_server();
-                }
-                Step::MarkHealthy(server_id) => {
-                    lb.healthy_server(server_id);
-                }
-                Step::MarkUnhealthy(server_id) => {
-                    lb.unhealthy_server(server_id);
-                }
-            }));
+    #[test]
+    fn test_servers_by_load_sorts_busiest_first() {
+        let servers = (0..4)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect();
+        let mut lb = LeastConnections::new(servers);
+        lb.connection_counts = vec![2, 5, 0, 2];
+        lb.unhealthy_server(1);
+        lb.connection_counts[2] = 7;
+
+        assert_eq!(lb.servers_by_load(), vec![(2, 7), (0, 2), (3, 2), (1, 0)]);
+    }
+
+    #[test]
+    fn test_on_connection_change_reports_every_change() {
+        let servers = (0..2)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect();
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let mut lb = LeastConnections::new(servers)
+            .on_connection_change(move |id, count| sink.lock().unwrap().push((id, count)));
+
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        lb.release_connections(&[0, 1, 1]);
+        lb.unhealthy_server(0);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![(0, 1), (1, 1), (0, 2), (0, 1), (1, 0), (0, 0)]
+        );
+    }
 
-            assert!(result.is_ok(), "Panic occurred with step: {:?}", step);
+    #[test]
+    fn test_added_server_receives_selections_until_caught_up() {
+        let mut lb = LeastConnections::new(
+            (0..2)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        for _ in 0..4 {
+            let _ = lb.select_server();
         }
+        lb.add_server(Server::new(2, ServerState::Healthy));
+
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 2 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 2 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.servers_by_load(), vec![(0, 3), (1, 2), (2, 2)]);
+
+        lb.remove_server(0);
+        assert_eq!(lb.servers_by_load(), vec![(1, 2), (2, 2)]);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+    }
+
+    #[test]
+    fn test_release_connection_steers_next_selection() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        for _ in 0..6 {
+            let _ = lb.select_server();
+        }
+        assert_eq!(lb.servers_by_load(), vec![(0, 2), (1, 2), (2, 2)]);
+
+        lb.release_connection(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+
+        lb.release_connection(2);
+        lb.release_connection(2);
+        lb.release_connection(2);
+        assert_eq!(lb.servers_by_load(), vec![(0, 2), (1, 2), (2, 0)]);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 2 });
+    }
+
+    #[test]
+    fn test_server_state_tracks_health() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        assert_eq!(lb.server_state(1), ServerState::Healthy);
+
+        lb.unhealthy_server(1);
+        assert_eq!(lb.server_state(0), ServerState::Healthy);
+        assert_eq!(lb.server_state(1), ServerState::Unhealthy);
+        assert_eq!(lb.server_state(2), ServerState::Healthy);
+
+        lb.healthy_server(1);
+        assert_eq!(lb.server_state(1), ServerState::Healthy);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_server_state_out_of_range_panics() {
+        let lb = LeastConnections::new(vec![Server::new(0, ServerState::Healthy)]);
+        let _ = lb.server_state(1);
+    }
+
+    #[test]
+    fn test_healthy_count_tracks_health_changes() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        assert_eq!(lb.healthy_count(), 3);
+
+        lb.unhealthy_server(0);
+        lb.unhealthy_server(2);
+        assert_eq!(lb.healthy_count(), 1);
+        lb.unhealthy_server(2);
+        assert_eq!(lb.healthy_count(), 1);
+
+        lb.unhealthy_server(1);
+        assert_eq!(lb.healthy_count(), 0);
+        assert!(!lb.has_quorum(1));
+
+        for id in 0..3 {
+            lb.healthy_server(id);
+        }
+        assert_eq!(lb.healthy_count(), 3);
+        assert!(lb.has_quorum(3));
+    }
+
+    #[test]
+    fn test_connections_after_uneven_selection_and_release() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.unhealthy_server(2);
+        for _ in 0..4 {
+            let _ = lb.select_server();
+        }
+        lb.healthy_server(2);
+        assert_eq!(
+            (0..3).map(|id| lb.connections(id)).collect::<Vec<_>>(),
+            vec![2, 2, 0]
+        );
+
+        for _ in 0..3 {
+            let _ = lb.select_server();
+        }
+        lb.release_connection(0);
+        lb.release_connection(0);
+        assert_eq!(
+            (0..3).map(|id| lb.connections(id)).collect::<Vec<_>>(),
+            vec![1, 2, 2]
+        );
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_connections_out_of_range_panics() {
+        let lb = LeastConnections::new(vec![Server::new(0, ServerState::Healthy)]);
+        let _ = lb.connections(1);
+    }
+
+    #[test]
+    fn test_ids_are_server_ids_not_positions() {
+        let mut lb = LeastConnections::new(
+            [10, 20, 30]
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .to_vec(),
+        );
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 10 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 20 });
+
+        lb.remove_server(20);
+        lb.unhealthy_server(10);
+        assert_eq!(lb.connections(10), 0);
+        assert_eq!(lb.server_state(30), ServerState::Healthy);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 30 });
+        assert_eq!(lb.connections(30), 1);
+        assert_eq!(lb.servers_by_load(), vec![(30, 1), (10, 0)]);
+    }
+
+    #[test]
+    fn test_server_at_capacity_is_skipped() {
+        let mut lb = LeastConnections::new(
+            (0..2)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.set_max_connections(0, Some(1));
+
+        let selected: Vec<_> = (0..4).map(|_| lb.select_server()).collect();
+        assert_eq!(
+            selected,
+            [0, 1, 1, 1]
+                .map(|id| LoadBalancerResult::Selected { id })
+                .to_vec()
+        );
+
+        lb.release_connection(0);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+    }
+
+    #[test]
+    fn test_all_saturated_is_overloaded() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.unhealthy_server(2);
+        lb.set_max_connections(0, Some(2));
+        lb.set_max_connections(1, Some(1));
+        for _ in 0..3 {
+            assert!(matches!(
+                lb.select_server(),
+                LoadBalancerResult::Selected { .. }
+            ));
+        }
+        assert_eq!(lb.select_server(), LoadBalancerResult::Overloaded);
+
+        lb.set_max_connections(1, None);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+
+        lb.unhealthy_server(0);
+        lb.unhealthy_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    fn test_draining_server_keeps_connections_but_gets_no_selections() {
+        let mut lb = LeastConnections::new(
+            (0..2)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        for _ in 0..4 {
+            let _ = lb.select_server();
+        }
+        assert_eq!(lb.connections(0), 2);
+
+        lb.drain_server(0);
+        assert_eq!(lb.server_state(0), ServerState::Draining);
+        assert_eq!(lb.healthy_count(), 1);
+        for _ in 0..5 {
+            assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        }
+        assert_eq!(lb.connections(0), 2);
+
+        lb.release_connection(0);
+        assert_eq!(lb.connections(0), 1);
+
+        lb.drain_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+
+        lb.healthy_server(0);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.connections(0), 2);
+    }
+
+    #[test]
+    fn test_set_states_resets_only_unhealthy_connections() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        for _ in 0..6 {
+            let _ = lb.select_server();
+        }
+        lb.set_states(&[
+            ServerState::Unhealthy,
+            ServerState::Draining,
+            ServerState::Healthy,
+        ]);
+        assert_eq!(lb.servers_by_load(), vec![(1, 2), (2, 2), (0, 0)]);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 2 });
+
+        lb.set_states(&[ServerState::Healthy; 3]);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+    }
+
+    #[test]
+    fn test_servers_reads_back_states() {
+        let mut lb = LeastConnections::new(vec![
+            Server::new(4, ServerState::Healthy),
+            Server::new(2, ServerState::Unhealthy),
+        ]);
+        lb.drain_server(4);
+
+        let states: Vec<_> = lb.servers().iter().map(|s| s.state).collect();
+        assert_eq!(states, vec![ServerState::Draining, ServerState::Unhealthy]);
+    }
+
+    #[test]
+    fn test_on_select_records_every_outcome() {
+        let servers = (0..2)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect();
+        let selections = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = selections.clone();
+        let mut lb =
+            LeastConnections::new(servers).on_select(move |result: &LoadBalancerResult| {
+                sink.lock().unwrap().push(result.clone())
+            });
+        lb.set_max_connections(1, Some(1));
+
+        let _ = lb.select_server();
+        let _ = lb.select_server();
+        let _ = lb.select_server();
+        lb.unhealthy_server(0);
+        let _ = lb.select_server();
+        lb.unhealthy_server(1);
+        let _ = lb.select_server();
+
+        assert_eq!(
+            *selections.lock().unwrap(),
+            vec![
+                LoadBalancerResult::Selected { id: 0 },
+                LoadBalancerResult::Selected { id: 1 },
+                LoadBalancerResult::Selected { id: 0 },
+                LoadBalancerResult::Overloaded,
+                LoadBalancerResult::NoHealthyServers,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_metrics_count_selections_and_outages() {
+        let mut lb = LeastConnections::new(
+            (0..2)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        for _ in 0..8 {
+            let _ = lb.select_server();
+        }
+        lb.unhealthy_server(0);
+        lb.unhealthy_server(1);
+        let _ = lb.select_server();
+
+        assert_eq!(
+            *lb.metrics(),
+            LbMetrics {
+                total_selections: 8,
+                per_server: vec![4, 4],
+                no_healthy: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_select_with_retry_moves_past_failing_server() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        let result = lb.select_with_retry(3, |id| id == 2);
+        assert_eq!(result, LoadBalancerResult::Selected { id: 2 });
+        assert_eq!(lb.servers_by_load(), vec![(0, 1), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn test_select_excluding_takes_least_loaded_of_the_rest() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.set_max_connections(2, Some(1));
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::Selected { id: 1 }
+        );
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::Selected { id: 2 }
+        );
+        assert_eq!(lb.select_excluding(&[0, 1]), LoadBalancerResult::Overloaded);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        lb.unhealthy_server(2);
+        assert_eq!(
+            lb.select_excluding(&[0, 1]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+
+    #[test]
+    fn test_snapshot_keeps_connection_counts() {
+        let mut lb = LeastConnections::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.set_max_connections(1, Some(1));
+        for _ in 0..4 {
+            let _ = lb.select_server();
+        }
+
+        let mut restored = LeastConnections::from_snapshot(lb.snapshot());
+        assert_eq!(restored.servers_by_load(), lb.servers_by_load());
+        for _ in 0..6 {
+            assert_eq!(restored.select_server(), lb.select_server());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_json_round_trip() {
+        let json = r#"[
+            {"id": 0, "state": "Healthy", "weight": 1, "metadata": "eu-west"},
+            {"id": 1, "state": "Healthy", "weight": 1, "metadata": "us-east"}
+        ]"#;
+        let mut lb: LeastConnections<String> = LeastConnections::from_servers_json(json).unwrap();
+        for _ in 0..3 {
+            let _ = lb.select_server();
+        }
+
+        let saved = serde_json::to_string(&lb.snapshot()).unwrap();
+        let mut restored: LeastConnections<String> =
+            LeastConnections::from_snapshot(serde_json::from_str(&saved).unwrap());
+        assert_eq!(restored.connections(0), 2);
+        assert_eq!(restored.server(1).metadata, "us-east");
+        for _ in 0..4 {
+            assert_eq!(restored.select_server(), lb.select_server());
+        }
+    }
+
+    #[test]
+    fn test_random_tie_break_spreads_first_picks() {
+        let servers = || {
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect::<Vec<_>>()
+        };
+        let first_picks: Vec<_> = (0..10)
+            .map(|seed| {
+                let mut lb =
+                    LeastConnections::new(servers()).random_tie_break(StdRng::seed_from_u64(seed));
+                lb.select_server()
+            })
+            .collect();
+        assert!(first_picks.iter().any(|pick| *pick != first_picks[0]));
+
+        let mut lb =
+            LeastConnections::new(servers()).random_tie_break(ScriptedRandom::new(vec![2, 0]));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 2 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+    }
+
+    #[test]
+    fn test_try_new_rejects_bad_server_lists() {
+        let empty: Vec<Server> = vec![];
+        assert_eq!(
+            LeastConnections::try_new(empty).err(),
+            Some(ConfigError::EmptyServerList)
+        );
+        let duplicate = vec![
+            Server::new(1, ServerState::Healthy),
+            Server::new(1, ServerState::Healthy),
+        ];
+        assert_eq!(
+            LeastConnections::try_new(duplicate).err(),
+            Some(ConfigError::DuplicateServerId(1))
+        );
     }
 }
@@ -0,0 +1,248 @@
+use crate::lb::Slots;
+use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+use std::time::Duration;
+
+/// Selects the healthy server with the lowest moving average of recorded
+/// latencies. Ties go to the server listed first.
+///
+/// Each `record_latency` updates the server's average as
+/// `decay * average + (1 - decay) * latency`, so a higher `decay` reacts more
+/// slowly. A server without samples is preferred over any measured one, so new
+/// and recovered servers get tried; marking a server unhealthy forgets its samples.
+pub struct LeastResponseTime<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
+    // Moving average in seconds, None until the first sample.
+    latencies: Vec<Option<f64>>,
+    decay: f64,
+}
+
+impl<T> LeastResponseTime<T> {
+    pub fn new(servers: Vec<Server<T>>, decay: f64) -> Self {
+        assert!(!servers.is_empty());
+        assert!((0.0..1.0).contains(&decay));
+        let latencies = vec![None; servers.len()];
+
+        Self {
+            slots: Slots::new(&servers),
+            servers,
+            latencies,
+            decay,
+        }
+    }
+
+    /// Moving average of `server_id`'s latency, `None` before its first sample.
+    pub fn average_latency(&self, server_id: usize) -> Option<Duration> {
+        let slot = self.slots.get(server_id);
+
+        self.latencies[slot].map(Duration::from_secs_f64)
+    }
+
+    fn select(&self, exclude: &[usize]) -> LoadBalancerResult {
+        assert!(!self.servers.is_empty());
+        assert!(self.latencies.len() == self.servers.len());
+
+        let mut best_server: Option<usize> = None;
+        for (i, server) in self.servers.iter().enumerate() {
+            if server.state != ServerState::Healthy || exclude.contains(&server.id) {
+                continue;
+            }
+            let faster = match (
+                best_server.map(|best| self.latencies[best]),
+                self.latencies[i],
+            ) {
+                (None, _) => true,
+                (Some(None), _) => false,
+                (Some(Some(_)), None) => true,
+                (Some(Some(best)), Some(latency)) => latency < best,
+            };
+            if faster {
+                best_server = Some(i);
+            }
+        }
+
+        match best_server {
+            Some(slot) => LoadBalancerResult::Selected {
+                id: self.servers[slot].id,
+            },
+            None => LoadBalancerResult::NoHealthyServers,
+        }
+    }
+}
+
+impl<T> LoadBalancer<T> for LeastResponseTime<T> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        self.select(&[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        self.select(exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Healthy;
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.latencies.len() == self.servers.len());
+
+        self.servers[slot].state = ServerState::Unhealthy;
+        self.latencies[slot] = None;
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Draining;
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+
+        for (slot, &state) in states.iter().enumerate() {
+            let server_id = self.servers[slot].id;
+            match state {
+                ServerState::Healthy => self.healthy_server(server_id),
+                ServerState::Unhealthy => self.unhealthy_server(server_id),
+                ServerState::Draining => self.drain_server(server_id),
+            }
+        }
+    }
+
+    fn record_latency(&mut self, server_id: usize, latency: Duration) {
+        let slot = self.slots.get(server_id);
+        assert!(self.latencies.len() == self.servers.len());
+
+        let sample = latency.as_secs_f64();
+        let average = match self.latencies[slot] {
+            Some(average) => self.decay * average + (1.0 - self.decay) * sample,
+            None => sample,
+        };
+        assert!(average >= 0.0);
+        self.latencies[slot] = Some(average);
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.slots.push(server.id, self.servers.len());
+        assert!(self.latencies.len() == self.servers.len());
+
+        self.servers.push(server);
+        self.latencies.push(None);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+
+        let slot = self.slots.remove(server_id);
+        self.servers.remove(slot);
+        self.latencies.remove(slot);
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_decay_of_one_panics() {
+        let _ = LeastResponseTime::new(servers(2), 1.0);
+    }
+
+    #[test]
+    fn test_high_latency_shifts_selections_away() {
+        let mut lb = LeastResponseTime::new(servers(2), 0.5);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        lb.record_latency(0, Duration::from_millis(200));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+
+        lb.record_latency(1, Duration::from_millis(20));
+        for _ in 0..10 {
+            assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        }
+    }
+
+    #[test]
+    fn test_average_decays_toward_recent_samples() {
+        let mut lb = LeastResponseTime::new(servers(2), 0.5);
+        lb.record_latency(0, Duration::from_millis(100));
+        lb.record_latency(1, Duration::from_millis(40));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+
+        lb.record_latency(0, Duration::from_millis(20));
+        assert_eq!(lb.average_latency(0), Some(Duration::from_millis(60)));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+
+        lb.record_latency(0, Duration::from_millis(10));
+        assert_eq!(lb.average_latency(0), Some(Duration::from_millis(35)));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+    }
+
+    #[test]
+    fn test_recovered_server_is_tried_optimistically() {
+        let mut lb = LeastResponseTime::new(servers(2), 0.5);
+        lb.record_latency(0, Duration::from_millis(10));
+        lb.record_latency(1, Duration::from_millis(500));
+        lb.unhealthy_server(1);
+        assert_eq!(lb.average_latency(1), None);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        lb.healthy_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+
+        lb.unhealthy_server(0);
+        lb.unhealthy_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    fn test_select_excluding_skips_fastest() {
+        let mut lb = LeastResponseTime::new(servers(3), 0.5);
+        lb.record_latency(0, Duration::from_millis(10));
+        lb.record_latency(1, Duration::from_millis(100));
+        lb.record_latency(2, Duration::from_millis(50));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        for _ in 0..10 {
+            assert_eq!(
+                lb.select_excluding(&[0]),
+                LoadBalancerResult::Selected { id: 2 }
+            );
+        }
+        assert_eq!(
+            lb.select_excluding(&[0, 1, 2]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+}
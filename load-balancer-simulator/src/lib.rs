@@ -1,9 +1,36 @@
 //! Load Balancer Simulator Library
 
+pub mod anti_affinity;
+pub mod bounded_skew;
+pub mod consistent_hashing;
+pub mod ip_hash;
 pub mod lb;
 pub mod least_connections;
+pub mod least_response_time;
+pub mod maglev;
+pub mod outlier_ejection;
+pub mod random;
+pub mod rendezvous_hashing;
 pub mod round_robin;
+pub mod sim;
+pub mod sync;
+pub mod tiered_round_robin;
+pub mod time;
+pub mod weighted_least_connections;
 
+pub use anti_affinity::*;
+pub use bounded_skew::*;
+pub use consistent_hashing::*;
+pub use ip_hash::*;
 pub use lb::*;
 pub use least_connections::*;
+pub use least_response_time::*;
+pub use maglev::*;
+pub use outlier_ejection::*;
+pub use random::*;
+pub use rendezvous_hashing::*;
 pub use round_robin::*;
+pub use sync::*;
+pub use tiered_round_robin::*;
+pub use time::*;
+pub use weighted_least_connections::*;
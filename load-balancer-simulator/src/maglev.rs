@@ -0,0 +1,337 @@
+use crate::lb::{hash_key, Slots};
+use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Maps each request key to a server through a Maglev lookup table of
+/// `table_size` entries, resolving `select_for` with one hash and one index.
+///
+/// Every healthy server fills table entries in its own pseudo-random order, taking
+/// turns, so each ends up with either `table_size / n` or one more entry. The
+/// table is rebuilt whenever a server joins, leaves or changes health, which
+/// remaps the keys of that server and only a few others. `table_size` must be a
+/// prime, and should be well above the server count for an even spread.
+pub struct Maglev<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
+    table_size: usize,
+    // Server id per entry; empty while no server is healthy.
+    table: Vec<usize>,
+    // Keys `select_server` hashes in place of a request key.
+    unkeyed_requests: u64,
+}
+
+impl<T> Maglev<T> {
+    pub fn new(servers: Vec<Server<T>>, table_size: usize) -> Self {
+        assert!(!servers.is_empty());
+        assert!(is_prime(table_size), "table size must be a prime");
+
+        let mut lb = Self {
+            slots: Slots::new(&servers),
+            servers,
+            table_size,
+            table: Vec::with_capacity(table_size),
+            unkeyed_requests: 0,
+        };
+        lb.rebuild();
+        lb
+    }
+
+    /// The next running-counter key `select_server` hashes in place of a request key.
+    fn unkeyed_key(&mut self) -> [u8; 8] {
+        let key = self.unkeyed_requests.to_le_bytes();
+        self.unkeyed_requests = self.unkeyed_requests.wrapping_add(1);
+        key
+    }
+
+    /// Selects for `key`, skipping servers in `exclude`.
+    fn select(&self, key: &[u8], exclude: &[usize]) -> LoadBalancerResult {
+        assert!(self.table.is_empty() || self.table.len() == self.table_size);
+
+        if self.table.is_empty() {
+            return LoadBalancerResult::NoHealthyServers;
+        }
+        // An excluded owner passes the key on to the owners of the entries after it.
+        let entry = (hash_key(key) % self.table_size as u64) as usize;
+        (0..self.table_size)
+            .map(|offset| self.table[(entry + offset) % self.table_size])
+            .find(|id| !exclude.contains(id))
+            .map_or(LoadBalancerResult::NoHealthyServers, |id| {
+                LoadBalancerResult::Selected { id }
+            })
+    }
+
+    fn rebuild(&mut self) {
+        let mut healthy: Vec<usize> = self
+            .servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .map(|s| s.id)
+            .collect();
+        // Filling in id order keeps the table independent of the order servers
+        // were added.
+        healthy.sort_unstable();
+
+        self.table.clear();
+        if healthy.is_empty() {
+            return;
+        }
+
+        let size = self.table_size as u64;
+        let mut entries: Vec<Option<usize>> = vec![None; self.table_size];
+        // (next position in the permutation, offset, skip) per healthy server.
+        let mut permutations: Vec<(u64, u64, u64)> = healthy
+            .iter()
+            .map(|&id| {
+                (
+                    0,
+                    seeded_hash(id, 0) % size,
+                    seeded_hash(id, 1) % (size - 1) + 1,
+                )
+            })
+            .collect();
+        let mut filled = 0;
+        'fill: loop {
+            for (&id, (next, offset, skip)) in healthy.iter().zip(&mut permutations) {
+                let mut entry = ((*offset + *next * *skip) % size) as usize;
+                while entries[entry].is_some() {
+                    *next += 1;
+                    entry = ((*offset + *next * *skip) % size) as usize;
+                }
+                entries[entry] = Some(id);
+                *next += 1;
+                filled += 1;
+                if filled == self.table_size {
+                    break 'fill;
+                }
+            }
+        }
+
+        self.table.extend(entries.into_iter().map(Option::unwrap));
+        assert!(self.table.len() == self.table_size);
+    }
+}
+
+fn seeded_hash(server_id: usize, seed: u8) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (server_id, seed).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn is_prime(n: usize) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|d| d * d <= n)
+            .all(|d| !n.is_multiple_of(d))
+}
+
+impl<T> LoadBalancer<T> for Maglev<T> {
+    /// Without a key, successive calls are spread over the table by hashing a
+    /// running counter.
+    fn select_server(&mut self) -> LoadBalancerResult {
+        let key = self.unkeyed_key();
+        self.select(&key, &[])
+    }
+
+    fn select_for(&mut self, key: &[u8]) -> LoadBalancerResult {
+        self.select(key, &[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        let key = self.unkeyed_key();
+        self.select(&key, exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        if self.servers[slot].state != ServerState::Healthy {
+            self.servers[slot].state = ServerState::Healthy;
+            self.rebuild();
+        }
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        let was_healthy = self.servers[slot].state == ServerState::Healthy;
+        self.servers[slot].state = ServerState::Unhealthy;
+        if was_healthy {
+            self.rebuild();
+        }
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        let was_healthy = self.servers[slot].state == ServerState::Healthy;
+        self.servers[slot].state = ServerState::Draining;
+        if was_healthy {
+            self.rebuild();
+        }
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+
+        for (server, &state) in self.servers.iter_mut().zip(states) {
+            server.state = state;
+        }
+        self.rebuild();
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.slots.push(server.id, self.servers.len());
+        let healthy = server.state == ServerState::Healthy;
+        self.servers.push(server);
+        if healthy {
+            self.rebuild();
+        }
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+
+        let slot = self.slots.remove(server_id);
+        if self.servers.remove(slot).state == ServerState::Healthy {
+            self.rebuild();
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim;
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    fn keys(count: usize) -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|i| format!("user-{i}").into_bytes())
+            .collect()
+    }
+
+    fn selected_id(result: LoadBalancerResult) -> usize {
+        match result {
+            LoadBalancerResult::Selected { id } => id,
+            other => panic!("expected a selection, got {other:?}"),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_empty_servers_panics() {
+        let _: Maglev = Maglev::new(vec![], 101);
+    }
+
+    #[test]
+    #[should_panic(expected = "table size must be a prime")]
+    fn test_new_composite_table_size_panics() {
+        let _ = Maglev::new(servers(3), 100);
+    }
+
+    #[test]
+    fn test_table_entries_are_balanced() {
+        let lb = Maglev::new(servers(5), 1031);
+        let shares = sim::distribution(&lb.table, 5);
+        for share in shares {
+            assert!((share * 1031.0 - 1031.0 / 5.0).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_keys_spread_evenly() {
+        let mut lb = Maglev::new(servers(5), 1031);
+        let selections: Vec<usize> = keys(10_000)
+            .iter()
+            .map(|key| selected_id(lb.select_for(key)))
+            .collect();
+        for share in sim::distribution(&selections, 5) {
+            assert!((0.17..0.23).contains(&share));
+        }
+        assert_eq!(selected_id(lb.select_for(b"user-7")), selections[7]);
+    }
+
+    #[test]
+    fn test_removed_server_remaps_few_other_keys() {
+        let mut lb = Maglev::new(servers(5), 1031);
+        let keys = keys(10_000);
+        let before: Vec<_> = keys
+            .iter()
+            .map(|key| selected_id(lb.select_for(key)))
+            .collect();
+
+        lb.remove_server(3);
+        let mut moved = 0;
+        for (key, &before) in keys.iter().zip(&before) {
+            let after = selected_id(lb.select_for(key));
+            assert_ne!(after, 3);
+            if before != 3 && after != before {
+                moved += 1;
+            }
+        }
+        assert!(moved < keys.len() / 20);
+
+        lb.add_server(Server::new(3, ServerState::Healthy));
+        let restored: Vec<_> = keys
+            .iter()
+            .map(|key| selected_id(lb.select_for(key)))
+            .collect();
+        assert_eq!(restored, before);
+    }
+
+    #[test]
+    fn test_all_unhealthy_returns_no_healthy_servers() {
+        let mut lb = Maglev::new(servers(2), 101);
+        lb.unhealthy_server(0);
+        lb.drain_server(1);
+        assert_eq!(lb.select_for(b"key"), LoadBalancerResult::NoHealthyServers);
+
+        lb.healthy_server(1);
+        assert_eq!(
+            lb.select_for(b"key"),
+            LoadBalancerResult::Selected { id: 1 }
+        );
+    }
+
+    #[test]
+    fn test_select_excluding_always_avoids_excluded() {
+        let mut lb = Maglev::new(servers(2), 101);
+        for _ in 0..100 {
+            assert_eq!(
+                lb.select_excluding(&[0]),
+                LoadBalancerResult::Selected { id: 1 }
+            );
+        }
+        lb.unhealthy_server(1);
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+}
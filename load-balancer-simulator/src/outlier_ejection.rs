@@ -0,0 +1,292 @@
+use crate::{Clock, LoadBalancer, LoadBalancerResult, Server, ServerState};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Wraps a strategy so that a server failing too often is marked unhealthy, then
+/// marked healthy again once `ejection_interval` has passed, in the manner of
+/// Envoy's outlier detection.
+///
+/// Callers report call outcomes through `record_result`. A server is ejected once
+/// it reaches the `consecutive_failures` threshold, or once `failure_rate`
+/// percent of its last `window` results are failures. Only healthy servers are
+/// ejected, so a draining server stays draining. Marking a server by hand cancels
+/// its pending re-admission.
+pub struct OutlierEjection<L> {
+    inner: L,
+    clock: Box<dyn Clock>,
+    ejection_interval: Duration,
+    consecutive_failures: Option<u32>,
+    failure_rate: Option<(u8, usize)>,
+    outcomes: HashMap<usize, Outcomes>,
+    // When each ejected server was ejected.
+    ejected: HashMap<usize, Instant>,
+}
+
+#[derive(Debug, Default)]
+struct Outcomes {
+    consecutive_failures: u32,
+    // Most recent results, `true` for success, at most the failure rate window.
+    recent: VecDeque<bool>,
+}
+
+impl<L> OutlierEjection<L> {
+    /// Without a threshold nothing is ejected; add one with `consecutive_failures`
+    /// or `failure_rate`.
+    pub fn new(inner: L, ejection_interval: Duration, clock: impl Clock + 'static) -> Self {
+        assert!(!ejection_interval.is_zero());
+
+        Self {
+            inner,
+            clock: Box::new(clock),
+            ejection_interval,
+            consecutive_failures: None,
+            failure_rate: None,
+            outcomes: HashMap::new(),
+            ejected: HashMap::new(),
+        }
+    }
+
+    /// Ejects a server after `threshold` failures in a row.
+    pub fn consecutive_failures(mut self, threshold: u32) -> Self {
+        assert!(threshold > 0);
+
+        self.consecutive_failures = Some(threshold);
+        self
+    }
+
+    /// Ejects a server once at least `percent` of its last `window` results are
+    /// failures. Only a full window is judged.
+    pub fn failure_rate(mut self, percent: u8, window: usize) -> Self {
+        assert!(percent > 0 && percent <= 100);
+        assert!(window > 0);
+
+        self.failure_rate = Some((percent, window));
+        self
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    /// Whether `server_id` is currently ejected and waiting for re-admission.
+    pub fn is_ejected(&self, server_id: usize) -> bool {
+        self.ejected.contains_key(&server_id)
+    }
+
+    /// Records the outcome of a call to `server_id`, ejecting it if that crosses a
+    /// threshold. Results for an ejected server are ignored.
+    pub fn record_result<T>(&mut self, server_id: usize, ok: bool)
+    where
+        L: LoadBalancer<T>,
+    {
+        if self.ejected.contains_key(&server_id) {
+            return;
+        }
+        let healthy = self.inner.server_state(server_id) == ServerState::Healthy;
+
+        let outcomes = self.outcomes.entry(server_id).or_default();
+        if ok {
+            outcomes.consecutive_failures = 0;
+        } else {
+            outcomes.consecutive_failures += 1;
+        }
+        if let Some((_, window)) = self.failure_rate {
+            if outcomes.recent.len() == window {
+                outcomes.recent.pop_front();
+            }
+            outcomes.recent.push_back(ok);
+        }
+
+        let too_many_in_a_row = self
+            .consecutive_failures
+            .is_some_and(|threshold| outcomes.consecutive_failures >= threshold);
+        let rate_too_high = self.failure_rate.is_some_and(|(percent, window)| {
+            assert!(outcomes.recent.len() <= window);
+            let failures = outcomes.recent.iter().filter(|&&ok| !ok).count();
+            outcomes.recent.len() == window && failures * 100 >= usize::from(percent) * window
+        });
+
+        if healthy && (too_many_in_a_row || rate_too_high) {
+            self.inner.unhealthy_server(server_id);
+            self.outcomes.remove(&server_id);
+            self.ejected.insert(server_id, self.clock.now());
+        }
+    }
+
+    fn readmit<T>(&mut self)
+    where
+        L: LoadBalancer<T>,
+    {
+        let now = self.clock.now();
+        let interval = self.ejection_interval;
+        let due: Vec<usize> = self
+            .ejected
+            .iter()
+            .filter(|(_, &at)| now.saturating_duration_since(at) >= interval)
+            .map(|(&id, _)| id)
+            .collect();
+        for server_id in due {
+            self.ejected.remove(&server_id);
+            self.inner.healthy_server(server_id);
+        }
+    }
+}
+
+impl<T, L: LoadBalancer<T>> LoadBalancer<T> for OutlierEjection<L> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        self.readmit();
+        self.inner.select_server()
+    }
+
+    fn select_for(&mut self, key: &[u8]) -> LoadBalancerResult {
+        self.readmit();
+        self.inner.select_for(key)
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        self.readmit();
+        self.inner.select_excluding(exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        self.ejected.remove(&server_id);
+        self.inner.healthy_server(server_id);
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        self.ejected.remove(&server_id);
+        self.inner.unhealthy_server(server_id);
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        self.ejected.remove(&server_id);
+        self.inner.drain_server(server_id);
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        self.ejected.clear();
+        self.inner.set_states(states);
+    }
+
+    fn record_latency(&mut self, server_id: usize, latency: Duration) {
+        self.inner.record_latency(server_id, latency);
+    }
+
+    fn release_connection(&mut self, server_id: usize) {
+        self.inner.release_connection(server_id);
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.inner.add_server(server);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        self.inner.remove_server(server_id);
+        self.outcomes.remove(&server_id);
+        self.ejected.remove(&server_id);
+    }
+
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        self.inner.server(server_id)
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        self.inner.servers()
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.inner.healthy_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RoundRobin, TestClock};
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    #[test]
+    fn test_consecutive_failures_eject_until_interval_passes() {
+        let clock = TestClock::new();
+        let mut lb = OutlierEjection::new(
+            RoundRobin::new(servers(2)),
+            Duration::from_secs(30),
+            clock.clone(),
+        )
+        .consecutive_failures(3);
+
+        lb.record_result(1, false);
+        lb.record_result(1, false);
+        lb.record_result(1, true);
+        lb.record_result(1, false);
+        lb.record_result(1, false);
+        assert!(!lb.is_ejected(1));
+
+        lb.record_result(1, false);
+        assert!(lb.is_ejected(1));
+        assert_eq!(lb.server_state(1), ServerState::Unhealthy);
+        for _ in 0..4 {
+            assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        }
+
+        clock.advance(Duration::from_secs(29));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        assert!(!lb.is_ejected(1));
+        assert_eq!(lb.server_state(1), ServerState::Healthy);
+    }
+
+    #[test]
+    fn test_failure_rate_judges_full_window() {
+        let clock = TestClock::new();
+        let mut lb = OutlierEjection::new(
+            RoundRobin::new(servers(2)),
+            Duration::from_secs(10),
+            clock.clone(),
+        )
+        .failure_rate(50, 4);
+
+        lb.record_result(0, false);
+        lb.record_result(0, false);
+        lb.record_result(0, true);
+        assert!(!lb.is_ejected(0));
+
+        lb.record_result(0, true);
+        assert!(lb.is_ejected(0));
+        assert_eq!(lb.healthy_count(), 1);
+    }
+
+    #[test]
+    fn test_manual_marking_cancels_readmission() {
+        let clock = TestClock::new();
+        let mut lb = OutlierEjection::new(
+            RoundRobin::new(servers(2)),
+            Duration::from_secs(10),
+            clock.clone(),
+        )
+        .consecutive_failures(1);
+
+        lb.record_result(0, false);
+        assert!(lb.is_ejected(0));
+        lb.unhealthy_server(0);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        assert_eq!(lb.server_state(0), ServerState::Unhealthy);
+
+        lb.drain_server(1);
+        lb.record_result(1, false);
+        assert!(!lb.is_ejected(1));
+        assert_eq!(lb.server_state(1), ServerState::Draining);
+    }
+}
@@ -0,0 +1,82 @@
+//! Injectable randomness, so randomized balancers and simulations stay
+//! deterministic under test.
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+/// Source of uniform random indices, in the spirit of `Clock`.
+pub trait RandomSource {
+    /// Returns a value in `[0, bound)`. Panics if `bound` is zero.
+    fn next_index(&mut self, bound: usize) -> usize;
+}
+
+impl RandomSource for StdRng {
+    fn next_index(&mut self, bound: usize) -> usize {
+        assert!(bound > 0);
+
+        self.random_range(0..bound)
+    }
+}
+
+/// Replays a fixed list of indices, for tests that need an exact sequence.
+/// Panics when the script runs out or a value is out of the requested bound.
+#[derive(Debug, Clone)]
+pub struct ScriptedRandom {
+    values: Vec<usize>,
+    next: usize,
+}
+
+impl ScriptedRandom {
+    pub fn new(values: Vec<usize>) -> Self {
+        Self { values, next: 0 }
+    }
+}
+
+impl RandomSource for ScriptedRandom {
+    fn next_index(&mut self, bound: usize) -> usize {
+        assert!(bound > 0);
+        assert!(self.next < self.values.len(), "random script exhausted");
+
+        let value = self.values[self.next];
+        assert!(value < bound, "scripted index {value} out of bound {bound}");
+        self.next += 1;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn draw(source: &mut impl RandomSource, bound: usize, count: usize) -> Vec<usize> {
+        (0..count).map(|_| source.next_index(bound)).collect()
+    }
+
+    #[test]
+    fn test_scripted_random_replays_values() {
+        let mut source = ScriptedRandom::new(vec![2, 0, 1]);
+        assert_eq!(draw(&mut source, 3, 3), vec![2, 0, 1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_scripted_random_out_of_bound_panics() {
+        let mut source = ScriptedRandom::new(vec![3]);
+        let _ = source.next_index(3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_scripted_random_exhausted_panics() {
+        let mut source = ScriptedRandom::new(vec![0]);
+        let _ = draw(&mut source, 1, 2);
+    }
+
+    #[test]
+    fn test_std_rng_is_reproducible_from_seed() {
+        let first = draw(&mut StdRng::seed_from_u64(7), 10, 20);
+        assert_eq!(draw(&mut StdRng::seed_from_u64(7), 10, 20), first);
+        assert!(first.iter().all(|&index| index < 10));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::lb::Slots;
+use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Maps each request key to the healthy server with the highest score
+/// `hash(key, server id)`, also known as highest-random-weight hashing.
+///
+/// Like `ConsistentHashing`, a server going unhealthy, being added or being
+/// removed only remaps the keys it wins or loses, but without a ring of virtual
+/// nodes: each selection scores every server instead.
+pub struct RendezvousHashing<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
+    // Keys `select_server` hashes in place of a request key.
+    unkeyed_requests: u64,
+}
+
+impl<T> RendezvousHashing<T> {
+    pub fn new(servers: Vec<Server<T>>) -> Self {
+        assert!(!servers.is_empty());
+
+        Self {
+            slots: Slots::new(&servers),
+            servers,
+            unkeyed_requests: 0,
+        }
+    }
+
+    /// The next running-counter key `select_server` hashes in place of a request key.
+    fn unkeyed_key(&mut self) -> [u8; 8] {
+        let key = self.unkeyed_requests.to_le_bytes();
+        self.unkeyed_requests = self.unkeyed_requests.wrapping_add(1);
+        key
+    }
+
+    /// Selects for `key`, skipping servers in `exclude`.
+    fn select(&self, key: &[u8], exclude: &[usize]) -> LoadBalancerResult {
+        assert!(!self.servers.is_empty());
+
+        // Ties on score, however unlikely, go to the lower id so the winner does
+        // not depend on the order servers were added.
+        let winner = self
+            .servers
+            .iter()
+            .filter(|server| server.state == ServerState::Healthy)
+            .filter(|server| !exclude.contains(&server.id))
+            .max_by_key(|server| (score(key, server.id), std::cmp::Reverse(server.id)));
+
+        match winner {
+            Some(server) => LoadBalancerResult::Selected { id: server.id },
+            None => LoadBalancerResult::NoHealthyServers,
+        }
+    }
+}
+
+fn score(key: &[u8], server_id: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(key);
+    server_id.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<T> LoadBalancer<T> for RendezvousHashing<T> {
+    /// Without a key, successive calls are spread by hashing a running counter.
+    fn select_server(&mut self) -> LoadBalancerResult {
+        let key = self.unkeyed_key();
+        self.select(&key, &[])
+    }
+
+    fn select_for(&mut self, key: &[u8]) -> LoadBalancerResult {
+        self.select(key, &[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        let key = self.unkeyed_key();
+        self.select(&key, exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Healthy;
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Unhealthy;
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Draining;
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+
+        for (server, &state) in self.servers.iter_mut().zip(states) {
+            server.state = state;
+        }
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.slots.push(server.id, self.servers.len());
+        self.servers.push(server);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+
+        let slot = self.slots.remove(server_id);
+        self.servers.remove(slot);
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    fn selected_id(result: LoadBalancerResult) -> usize {
+        match result {
+            LoadBalancerResult::Selected { id } => id,
+            other => panic!("expected a selection, got {other:?}"),
+        }
+    }
+
+    fn assignments(lb: &mut RendezvousHashing, keys: u32) -> Vec<usize> {
+        (0..keys)
+            .map(|key| selected_id(lb.select_for(&key.to_le_bytes())))
+            .collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_empty_servers_panics() {
+        let _: RendezvousHashing = RendezvousHashing::new(vec![]);
+    }
+
+    #[test]
+    fn test_same_key_selects_same_server() {
+        let mut lb = RendezvousHashing::new(servers(5));
+        let key = b"session-42";
+        let first = lb.select_for(key);
+        for _ in 0..20 {
+            let _ = lb.select_server();
+            assert_eq!(lb.select_for(key), first);
+        }
+    }
+
+    #[test]
+    fn test_removing_server_moves_only_its_keys() {
+        let mut lb = RendezvousHashing::new(servers(4));
+        let before = assignments(&mut lb, 200);
+        assert!((0..4).all(|id| before.contains(&id)));
+
+        lb.remove_server(2);
+        let after = assignments(&mut lb, 200);
+        for (old, new) in before.iter().zip(&after) {
+            if *old == 2 {
+                assert_ne!(*new, 2);
+            } else {
+                assert_eq!(old, new);
+            }
+        }
+
+        lb.add_server(Server::new(2, ServerState::Healthy));
+        assert_eq!(assignments(&mut lb, 200), before);
+    }
+
+    #[test]
+    fn test_unhealthy_server_is_skipped_until_recovered() {
+        let mut lb = RendezvousHashing::new(servers(3));
+        let key = [10, 0, 0, 1];
+        let top = selected_id(lb.select_for(&key));
+
+        lb.unhealthy_server(top);
+        let runner_up = selected_id(lb.select_for(&key));
+        assert_ne!(runner_up, top);
+
+        lb.drain_server(runner_up);
+        let third = selected_id(lb.select_for(&key));
+        assert!(third != top && third != runner_up);
+
+        lb.healthy_server(top);
+        assert_eq!(selected_id(lb.select_for(&key)), top);
+
+        lb.set_states(&[ServerState::Unhealthy; 3]);
+        assert_eq!(lb.select_for(&key), LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    fn test_select_excluding_falls_back_to_other_servers() {
+        let mut lb = RendezvousHashing::new(servers(3));
+        assert!(matches!(
+            lb.select_excluding(&[1]),
+            LoadBalancerResult::Selected { id } if id != 1
+        ));
+        assert_eq!(
+            lb.select_excluding(&[0, 1, 2]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+
+    #[test]
+    fn test_select_excluding_always_avoids_excluded() {
+        let mut lb = RendezvousHashing::new(servers(2));
+        for _ in 0..100 {
+            assert_eq!(
+                lb.select_excluding(&[0]),
+                LoadBalancerResult::Selected { id: 1 }
+            );
+        }
+        lb.unhealthy_server(1);
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+}
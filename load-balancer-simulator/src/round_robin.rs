@@ -1,29 +1,127 @@
-use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+use crate::lb::{SelectHook, Slots};
+use crate::{ConfigError, LbMetrics, LoadBalancer, LoadBalancerResult, Server, ServerState};
 
-pub struct RoundRobin {
-    servers: Vec<Server>,
+pub struct RoundRobin<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
     current_index: usize,
+    // Servers out of rotation, draining ones included.
     unhealthy_count: usize,
+    on_select: Option<SelectHook>,
+    metrics: LbMetrics,
 }
 
-impl RoundRobin {
-    pub fn new(servers: Vec<Server>) -> Self {
-        assert!(!servers.is_empty());
+/// What `RoundRobin` needs to carry on selecting where it left off. The
+/// `on_select` callback and metrics are not part of it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoundRobinSnapshot<T = ()> {
+    pub servers: Vec<Server<T>>,
+    pub current_index: usize,
+    pub unhealthy_count: usize,
+}
+
+impl<T> RoundRobin<T> {
+    /// Panics on a server list `try_new` would reject.
+    pub fn new(servers: Vec<Server<T>>) -> Self {
+        Self::try_new(servers).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    pub fn try_new(servers: Vec<Server<T>>) -> Result<Self, ConfigError> {
+        if servers.is_empty() {
+            return Err(ConfigError::EmptyServerList);
+        }
         let unhealthy_count = servers
             .iter()
-            .filter(|s| s.state == ServerState::Unhealthy)
+            .filter(|s| s.state != ServerState::Healthy)
             .count();
 
-        Self {
+        Ok(Self {
+            slots: Slots::try_new(&servers)?,
+            metrics: LbMetrics::new(servers.len()),
             servers,
             current_index: 0,
             unhealthy_count,
+            on_select: None,
+        })
+    }
+
+    /// Starts the rotation at the server in slot `position`, e.g. one saved with
+    /// `position` before a config reload.
+    pub fn with_position(servers: Vec<Server<T>>, position: usize) -> Self {
+        let mut lb = Self::new(servers);
+        assert!(position < lb.servers.len());
+
+        lb.current_index = position;
+        lb
+    }
+
+    /// Slot of the server the rotation tries next.
+    pub fn position(&self) -> usize {
+        self.current_index
+    }
+
+    /// Calls `f` with the outcome of every `select_server`, including
+    /// `NoHealthyServers`.
+    pub fn on_select(mut self, f: impl FnMut(&LoadBalancerResult) + Send + 'static) -> Self {
+        self.on_select = Some(Box::new(f));
+        self
+    }
+
+    pub fn metrics(&self) -> &LbMetrics {
+        &self.metrics
+    }
+
+    /// Resumes from `snapshot`. Panics if its fields disagree with each other.
+    pub fn from_snapshot(snapshot: RoundRobinSnapshot<T>) -> Self {
+        let mut lb = Self::new(snapshot.servers);
+        assert!(snapshot.current_index < lb.servers.len());
+        assert!(snapshot.unhealthy_count == lb.unhealthy_count);
+
+        lb.current_index = snapshot.current_index;
+        lb
+    }
+}
+
+impl<T: Clone> RoundRobin<T> {
+    pub fn snapshot(&self) -> RoundRobinSnapshot<T> {
+        RoundRobinSnapshot {
+            servers: self.servers.clone(),
+            current_index: self.current_index,
+            unhealthy_count: self.unhealthy_count,
         }
     }
 }
 
-impl LoadBalancer for RoundRobin {
-    fn select_server(&mut self) -> LoadBalancerResult {
+#[cfg(feature = "serde")]
+impl<T: serde::de::DeserializeOwned> RoundRobin<T> {
+    /// A balancer over the JSON array of servers in `json`. Panics if the array is
+    /// empty, like `new`.
+    pub fn from_servers_json(json: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self::new(serde_json::from_str(json)?))
+    }
+}
+
+impl<T> RoundRobin<T> {
+    fn scan_to_healthy(&mut self) -> LoadBalancerResult {
+        assert!(self.unhealthy_count < self.servers.len());
+
+        while self.servers[self.current_index].state != ServerState::Healthy {
+            self.advance();
+        }
+
+        let id = self.servers[self.current_index].id;
+        self.advance();
+        LoadBalancerResult::Selected { id }
+    }
+
+    fn advance(&mut self) {
+        self.current_index = (self.current_index + 1) % self.servers.len();
+    }
+}
+
+impl<T> RoundRobin<T> {
+    fn select(&mut self) -> LoadBalancerResult {
         assert!(!self.servers.is_empty());
         assert!(self.current_index < self.servers.len());
         assert!(self.unhealthy_count <= self.servers.len());
@@ -32,91 +130,163 @@ impl LoadBalancer for RoundRobin {
             return LoadBalancerResult::NoHealthyServers;
         }
 
-        while self.servers[self.current_index].state == ServerState::Unhealthy {
-            self.current_index = (self.current_index + 1) % self.servers.len();
+        // Every server is healthy, so the cursor already points at one.
+        if self.unhealthy_count == 0 {
+            assert!(self.servers[self.current_index].state == ServerState::Healthy);
+            let id = self.servers[self.current_index].id;
+            self.advance();
+            return LoadBalancerResult::Selected { id };
+        }
+
+        self.scan_to_healthy()
+    }
+
+    fn select_skipping(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        assert!(self.current_index < self.servers.len());
+
+        for _ in 0..self.servers.len() {
+            let server = &self.servers[self.current_index];
+            let id = server.id;
+            let eligible = server.state == ServerState::Healthy && !exclude.contains(&id);
+            self.advance();
+            if eligible {
+                return LoadBalancerResult::Selected { id };
+            }
         }
+        LoadBalancerResult::NoHealthyServers
+    }
 
-        LoadBalancerResult::Selected {
-            id: self.current_index,
+    fn report(&mut self, result: LoadBalancerResult) -> LoadBalancerResult {
+        self.metrics.record(&result, &self.slots);
+        if let Some(on_select) = &mut self.on_select {
+            on_select(&result);
         }
+        result
+    }
+}
+
+impl<T> LoadBalancer<T> for RoundRobin<T> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        let result = self.select();
+        self.report(result)
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        let result = self.select_skipping(exclude);
+        self.report(result)
     }
 
     fn healthy_server(&mut self, server_id: usize) {
-        assert!(server_id < self.servers.len());
+        let slot = self.slots.get(server_id);
         assert!(self.unhealthy_count <= self.servers.len());
 
-        if self.servers[server_id].state == ServerState::Unhealthy {
+        if self.servers[slot].state != ServerState::Healthy {
             self.unhealthy_count -= 1;
-            self.servers[server_id].state = ServerState::Healthy;
+            self.servers[slot].state = ServerState::Healthy;
         }
     }
 
     fn unhealthy_server(&mut self, server_id: usize) {
-        assert!(server_id < self.servers.len());
+        let slot = self.slots.get(server_id);
+        assert!(self.unhealthy_count <= self.servers.len());
+
+        if self.servers[slot].state == ServerState::Healthy {
+            self.unhealthy_count += 1;
+        }
+        self.servers[slot].state = ServerState::Unhealthy;
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
         assert!(self.unhealthy_count <= self.servers.len());
 
-        if self.servers[server_id].state == ServerState::Healthy {
+        if self.servers[slot].state == ServerState::Healthy {
             self.unhealthy_count += 1;
-            self.servers[server_id].state = ServerState::Unhealthy;
         }
+        self.servers[slot].state = ServerState::Draining;
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+
+        for (server, &state) in self.servers.iter_mut().zip(states) {
+            server.state = state;
+        }
+        self.unhealthy_count = self
+            .servers
+            .iter()
+            .filter(|s| s.state != ServerState::Healthy)
+            .count();
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        if server.state != ServerState::Healthy {
+            self.unhealthy_count += 1;
+        }
+        self.slots.push(server.id, self.servers.len());
+        self.servers.push(server);
+        self.metrics.per_server.push(0);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+
+        let slot = self.slots.remove(server_id);
+        if self.servers.remove(slot).state != ServerState::Healthy {
+            self.unhealthy_count -= 1;
+        }
+        self.metrics.per_server.remove(slot);
+        // Keep the cursor on the server it pointed at, or its successor.
+        if self.current_index > slot {
+            self.current_index -= 1;
+        }
+        if self.current_index == self.servers.len() {
+            self.current_index = 0;
+        }
+        assert!(self.unhealthy_count <= self.servers.len());
     }
 
     fn count(&self) -> usize {
         self.servers.len()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::rngs::StdRng;
-    use rand::{Rng, SeedableRng};
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    enum Step {
-        SelectServer,
-        MarkHealthy(usize),
-        MarkUnhealthy(usize),
+        &self.servers[slot]
     }
 
-    fn generate_random_steps(seed: u64, count: usize, server_count: usize) -> Vec<Step> {
-        let mut rng = StdRng::seed_from_u64(seed);
-        let mut steps = Vec::with_capacity(count);
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
 
-        for _ in 0..count {
-            let choice = rng.random_range(0..3);
-            let step = match choice {
-                0 => Step::SelectServer,
-                1 => Step::MarkHealthy(rng.random_range(0..server_count)),
-                _ => Step::MarkUnhealthy(rng.random_range(0..server_count)),
-            };
-            steps.push(step);
-        }
+    fn healthy_count(&self) -> usize {
+        assert!(self.unhealthy_count <= self.servers.len());
 
-        steps
+        self.servers.len() - self.unhealthy_count
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim;
 
     #[test]
     #[should_panic]
     fn test_new_empty_servers_panics() {
-        let _ = RoundRobin::new(vec![]);
+        let _: RoundRobin = RoundRobin::new(vec![]);
     }
 
     #[test]
     fn test_new_one_server() {
-        let lb = RoundRobin::new(vec![Server {
-            id: 0,
-            state: ServerState::Healthy,
-        }]);
+        let lb = RoundRobin::new(vec![Server::new(0, ServerState::Healthy)]);
         assert_eq!(lb.count(), 1);
     }
 
     #[test]
     fn test_select_server() {
-        let mut lb = RoundRobin::new(vec![Server {
-            id: 0,
-            state: ServerState::Healthy,
-        }]);
+        let mut lb = RoundRobin::new(vec![Server::new(0, ServerState::Healthy)]);
         assert_eq!(lb.count(), 1);
 
         assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
@@ -128,6 +298,34 @@ mod tests {
         assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
     }
 
+    #[test]
+    fn test_has_quorum() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        assert!(lb.has_quorum(0));
+        assert!(lb.has_quorum(3));
+        assert!(!lb.has_quorum(4));
+
+        lb.unhealthy_server(0);
+        assert!(lb.has_quorum(2));
+        assert!(!lb.has_quorum(3));
+
+        lb.unhealthy_server(1);
+        assert!(lb.has_quorum(1));
+        assert!(!lb.has_quorum(2));
+
+        lb.unhealthy_server(2);
+        assert!(lb.has_quorum(0));
+        assert!(!lb.has_quorum(1));
+
+        lb.healthy_server(1);
+        assert!(lb.has_quorum(1));
+        assert!(!lb.has_quorum(2));
+    }
+
     #[test]
     fn test_round_robin_random_sequence() {
         let server_count = 5;
@@ -135,29 +333,511 @@ mod tests {
         let count: usize = 100_000;
 
         let servers = (0..server_count)
-            .map(|id| Server {
-                id,
-                state: ServerState::Healthy,
-            })
+            .map(|id| Server::new(id, ServerState::Healthy))
             .collect();
 
         let mut lb = RoundRobin::new(servers);
-        let steps = generate_random_steps(seed, count, server_count);
-
-        for step in steps {
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| match step {
-                Step::SelectServer => {
-                    lb.select_server();
-                }
-                Step::MarkHealthy(server_id) => {
-                    lb.healthy_server(server_id);
-                }
-                Step::MarkUnhealthy(server_id) => {
-                    lb.unhealthy_server(server_id);
-                }
-            }));
-
-            assert!(result.is_ok(), "Panic occurred with step: {:?}", step);
+        let steps = sim::random_steps(seed, count, server_count);
+
+        let results = sim::run_checked(&mut lb, &steps);
+        let selections = steps
+            .iter()
+            .filter(|step| **step == sim::LbStep::SelectServer)
+            .count();
+        assert_eq!(results.len(), selections);
+    }
+
+    #[test]
+    fn test_all_healthy_fast_path_matches_scan() {
+        let server_count = 10_000;
+        let servers = || {
+            (0..server_count)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect::<Vec<_>>()
+        };
+        let mut fast = RoundRobin::new(servers());
+        let mut recovered = RoundRobin::new(servers());
+        recovered.unhealthy_server(0);
+        recovered.healthy_server(0);
+
+        for id in 0..1000 {
+            if id == 500 {
+                recovered.unhealthy_server(700);
+                recovered.healthy_server(700);
+            }
+            let selected = fast.select_server();
+            assert_eq!(selected, LoadBalancerResult::Selected { id });
+            assert_eq!(selected, recovered.select_server());
+        }
+    }
+
+    #[test]
+    fn test_select_server_cycles_through_healthy_servers() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        let selected: Vec<_> = (0..7).map(|_| lb.select_server()).collect();
+        assert_eq!(
+            selected,
+            [0, 1, 2, 0, 1, 2, 0]
+                .map(|id| LoadBalancerResult::Selected { id })
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_select_server_skips_unhealthy_server() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.unhealthy_server(1);
+        let selected: Vec<_> = (0..6).map(|_| lb.select_server()).collect();
+        assert_eq!(
+            selected,
+            [0, 2, 0, 2, 0, 2]
+                .map(|id| LoadBalancerResult::Selected { id })
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_added_server_joins_rotation() {
+        let mut lb = RoundRobin::new(
+            (0..2)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        lb.add_server(Server::new(2, ServerState::Healthy));
+        assert_eq!(lb.count(), 3);
+
+        let selected: Vec<_> = (0..4).map(|_| lb.select_server()).collect();
+        assert_eq!(
+            selected,
+            [0, 1, 2, 0]
+                .map(|id| LoadBalancerResult::Selected { id })
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_remove_middle_server_keeps_ids_stable() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.unhealthy_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 2 });
+
+        lb.remove_server(1);
+        assert_eq!(lb.count(), 2);
+        assert!(lb.has_quorum(2));
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 2 });
+
+        lb.unhealthy_server(2);
+        assert_eq!(lb.server_state(2), ServerState::Unhealthy);
+        assert_eq!(lb.server_state(0), ServerState::Healthy);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        lb.remove_server(2);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate_server_id_panics() {
+        let mut lb = RoundRobin::new(vec![Server::new(7, ServerState::Healthy)]);
+        lb.add_server(Server::new(7, ServerState::Healthy));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_remove_only_server_panics() {
+        let mut lb = RoundRobin::new(vec![Server::new(0, ServerState::Healthy)]);
+        lb.remove_server(0);
+    }
+
+    #[test]
+    fn test_server_state_tracks_health() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        assert_eq!(lb.server_state(1), ServerState::Healthy);
+
+        lb.unhealthy_server(1);
+        assert_eq!(lb.server_state(0), ServerState::Healthy);
+        assert_eq!(lb.server_state(1), ServerState::Unhealthy);
+        assert_eq!(lb.server_state(2), ServerState::Healthy);
+
+        lb.healthy_server(1);
+        assert_eq!(lb.server_state(1), ServerState::Healthy);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_server_state_out_of_range_panics() {
+        let lb = RoundRobin::new(vec![Server::new(0, ServerState::Healthy)]);
+        let _ = lb.server_state(1);
+    }
+
+    #[test]
+    fn test_healthy_count_tracks_health_changes() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        assert_eq!(lb.healthy_count(), 3);
+
+        lb.unhealthy_server(0);
+        lb.unhealthy_server(2);
+        assert_eq!(lb.healthy_count(), 1);
+        lb.unhealthy_server(2);
+        assert_eq!(lb.healthy_count(), 1);
+
+        lb.unhealthy_server(1);
+        assert_eq!(lb.healthy_count(), 0);
+        assert!(!lb.has_quorum(1));
+
+        for id in 0..3 {
+            lb.healthy_server(id);
+        }
+        assert_eq!(lb.healthy_count(), 3);
+        assert!(lb.has_quorum(3));
+    }
+
+    #[test]
+    fn test_selection_reads_back_server_metadata() {
+        let mut lb = RoundRobin::new(
+            ["a.internal", "b.internal", "c.internal"]
+                .into_iter()
+                .enumerate()
+                .map(|(id, host)| Server::with_metadata(id, ServerState::Healthy, host.to_string()))
+                .collect(),
+        );
+        lb.unhealthy_server(1);
+
+        let hosts: Vec<_> = (0..3)
+            .map(|_| match lb.select_server() {
+                LoadBalancerResult::Selected { id } => lb.server(id).metadata.clone(),
+                other => panic!("expected a selection, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(hosts, vec!["a.internal", "c.internal", "a.internal"]);
+
+        lb.add_server(Server::with_metadata(
+            3,
+            ServerState::Healthy,
+            "d.internal".to_string(),
+        ));
+        assert_eq!(lb.server(3).metadata, "d.internal");
+    }
+
+    #[test]
+    fn test_draining_server_leaves_rotation() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.drain_server(1);
+        assert_eq!(lb.healthy_count(), 2);
+        let selected: Vec<_> = (0..4).map(|_| lb.select_server()).collect();
+        assert_eq!(
+            selected,
+            [0, 2, 0, 2]
+                .map(|id| LoadBalancerResult::Selected { id })
+                .to_vec()
+        );
+
+        lb.unhealthy_server(1);
+        assert_eq!(lb.healthy_count(), 2);
+        lb.healthy_server(1);
+        assert_eq!(lb.healthy_count(), 3);
+    }
+
+    #[test]
+    fn test_set_states_applies_snapshot() {
+        let mut lb = RoundRobin::new(
+            (0..4)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.unhealthy_server(3);
+        lb.set_states(&[
+            ServerState::Unhealthy,
+            ServerState::Healthy,
+            ServerState::Draining,
+            ServerState::Healthy,
+        ]);
+        assert_eq!(lb.healthy_count(), 2);
+        let selected: Vec<_> = (0..4).map(|_| lb.select_server()).collect();
+        assert_eq!(
+            selected,
+            [1, 3, 1, 3]
+                .map(|id| LoadBalancerResult::Selected { id })
+                .to_vec()
+        );
+
+        lb.set_states(&[ServerState::Unhealthy; 4]);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_states_length_mismatch_panics() {
+        let mut lb = RoundRobin::new(vec![Server::new(0, ServerState::Healthy)]);
+        lb.set_states(&[ServerState::Healthy, ServerState::Healthy]);
+    }
+
+    #[test]
+    fn test_servers_reads_back_states_in_order() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.unhealthy_server(1);
+        lb.add_server(Server::new(7, ServerState::Draining));
+        lb.remove_server(0);
+
+        let servers: Vec<_> = lb.servers().iter().map(|s| (s.id, s.state)).collect();
+        assert_eq!(
+            servers,
+            vec![
+                (1, ServerState::Unhealthy),
+                (2, ServerState::Healthy),
+                (7, ServerState::Draining),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_on_select_records_every_outcome() {
+        let servers = (0..2)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect();
+        let selections = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = selections.clone();
+        let mut lb = RoundRobin::new(servers).on_select(move |result: &LoadBalancerResult| {
+            sink.lock().unwrap().push(result.clone())
+        });
+
+        let _ = lb.select_server();
+        let _ = lb.select_server();
+        lb.unhealthy_server(0);
+        let _ = lb.select_server();
+        lb.unhealthy_server(1);
+        let _ = lb.select_server();
+
+        assert_eq!(
+            *selections.lock().unwrap(),
+            vec![
+                LoadBalancerResult::Selected { id: 0 },
+                LoadBalancerResult::Selected { id: 1 },
+                LoadBalancerResult::Selected { id: 1 },
+                LoadBalancerResult::NoHealthyServers,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_metrics_count_selections_and_outages() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        for _ in 0..30 {
+            let _ = lb.select_server();
+        }
+        lb.set_states(&[ServerState::Unhealthy; 3]);
+        for _ in 0..4 {
+            let _ = lb.select_server();
+        }
+        lb.healthy_server(2);
+        let _ = lb.select_server();
+
+        assert_eq!(
+            *lb.metrics(),
+            LbMetrics {
+                total_selections: 31,
+                per_server: vec![10, 10, 11],
+                no_healthy: 4,
+            }
+        );
+
+        lb.remove_server(1);
+        lb.add_server(Server::new(5, ServerState::Healthy));
+        assert_eq!(lb.metrics().per_server, vec![10, 11, 0]);
+    }
+
+    #[test]
+    fn test_select_with_retry_moves_past_failing_server() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        let mut attempts = Vec::new();
+        let result = lb.select_with_retry(3, |id| {
+            attempts.push(id);
+            id != 0
+        });
+        assert_eq!(result, LoadBalancerResult::Selected { id: 1 });
+        assert_eq!(attempts, vec![0, 1]);
+
+        let mut attempts = Vec::new();
+        let result = lb.select_with_retry(2, |id| {
+            attempts.push(id);
+            false
+        });
+        assert_eq!(result, LoadBalancerResult::NoHealthyServers);
+        assert_eq!(attempts, vec![2, 0]);
+    }
+
+    #[test]
+    fn test_select_with_retry_stops_when_servers_run_out() {
+        let mut lb = RoundRobin::new(
+            (0..2)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        let mut attempts = Vec::new();
+        let result = lb.select_with_retry(5, |id| {
+            attempts.push(id);
+            false
+        });
+        assert_eq!(result, LoadBalancerResult::NoHealthyServers);
+        assert_eq!(attempts, vec![0, 1]);
+
+        lb.set_states(&[ServerState::Unhealthy; 2]);
+        let result = lb.select_with_retry(5, |_| true);
+        assert_eq!(result, LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    fn test_select_excluding_skips_next_in_rotation() {
+        let mut lb = RoundRobin::new(
+            (0..4)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.unhealthy_server(1);
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::Selected { id: 2 }
+        );
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 3 });
+        assert_eq!(
+            lb.select_excluding(&[0, 3]),
+            LoadBalancerResult::Selected { id: 2 }
+        );
+        assert_eq!(
+            lb.select_excluding(&[0, 2, 3]),
+            LoadBalancerResult::NoHealthyServers
+        );
+        assert_eq!(lb.metrics().no_healthy, 1);
+    }
+
+    #[test]
+    fn test_snapshot_resumes_rotation() {
+        let mut lb = RoundRobin::new(
+            (0..3)
+                .map(|id| Server::new(id, ServerState::Healthy))
+                .collect(),
+        );
+        lb.unhealthy_server(2);
+        let _ = lb.select_server();
+
+        let mut restored = RoundRobin::from_snapshot(lb.snapshot());
+        for _ in 0..5 {
+            assert_eq!(restored.select_server(), lb.select_server());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_inconsistent_snapshot_panics() {
+        let lb = RoundRobin::new(vec![Server::new(0, ServerState::Healthy)]);
+        let mut snapshot = lb.snapshot();
+        snapshot.unhealthy_count = 1;
+        let _ = RoundRobin::from_snapshot(snapshot);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_servers_json_and_snapshot_round_trip() {
+        let json = r#"[
+            {"id": 4, "state": "Healthy", "weight": 1, "metadata": null},
+            {"id": 7, "state": "Unhealthy", "weight": 1, "metadata": null},
+            {"id": 9, "state": "Healthy", "weight": 2, "metadata": null}
+        ]"#;
+        let mut lb: RoundRobin = RoundRobin::from_servers_json(json).unwrap();
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 4 });
+
+        let saved = serde_json::to_string(&lb.snapshot()).unwrap();
+        let mut restored: RoundRobin =
+            RoundRobin::from_snapshot(serde_json::from_str(&saved).unwrap());
+        for _ in 0..5 {
+            assert_eq!(restored.select_server(), lb.select_server());
+        }
+        assert_eq!(restored.server(9).weight, 2);
+        assert!(RoundRobin::<()>::from_servers_json("[{\"id\": 1}]").is_err());
+    }
+
+    #[test]
+    fn test_try_new_rejects_bad_server_lists() {
+        let empty: Vec<Server> = vec![];
+        assert_eq!(
+            RoundRobin::try_new(empty).err(),
+            Some(ConfigError::EmptyServerList)
+        );
+        let duplicate = vec![
+            Server::new(4, ServerState::Healthy),
+            Server::new(4, ServerState::Unhealthy),
+        ];
+        assert_eq!(
+            RoundRobin::try_new(duplicate).err(),
+            Some(ConfigError::DuplicateServerId(4))
+        );
+        assert!(RoundRobin::try_new(vec![Server::new(0, ServerState::Healthy)]).is_ok());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_position_out_of_range_panics() {
+        let _ = RoundRobin::with_position(vec![Server::new(0, ServerState::Healthy)], 1);
+    }
+
+    #[test]
+    fn test_with_position_resumes_rotation() {
+        let servers: Vec<Server> = (0..3)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect();
+        let mut lb = RoundRobin::new(servers.clone());
+        assert_eq!(lb.position(), 0);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+
+        let mut rebuilt = RoundRobin::with_position(servers, lb.position());
+        for _ in 0..6 {
+            assert_eq!(rebuilt.select_server(), lb.select_server());
         }
+        assert_eq!(
+            rebuilt.select_server(),
+            LoadBalancerResult::Selected { id: 2 }
+        );
     }
 }
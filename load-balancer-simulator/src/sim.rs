@@ -0,0 +1,216 @@
+//! Simulation harness for load balancers
+
+use crate::{LoadBalancer, LoadBalancerResult, RandomSource, ServerState};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::panic::{self, AssertUnwindSafe};
+
+/// One operation on a balancer. Servers are addressed by id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LbStep {
+    SelectServer,
+    MarkHealthy(usize),
+    MarkUnhealthy(usize),
+}
+
+/// `count` uniformly random `LbStep`s over servers with ids `0..server_count`;
+/// the same seed always gives the same steps.
+pub fn random_steps(seed: u64, count: usize, server_count: usize) -> Vec<LbStep> {
+    assert!(server_count > 0);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut steps = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let step = match rng.next_index(3) {
+            0 => LbStep::SelectServer,
+            1 => LbStep::MarkHealthy(rng.next_index(server_count)),
+            _ => LbStep::MarkUnhealthy(rng.next_index(server_count)),
+        };
+        steps.push(step);
+    }
+
+    steps
+}
+
+/// Applies every step to `lb` and returns what each selection did; marking steps
+/// have no entry.
+pub fn run_simulation<T, L: LoadBalancer<T>>(
+    lb: &mut L,
+    steps: &[LbStep],
+) -> Vec<LoadBalancerResult> {
+    steps.iter().filter_map(|step| apply(lb, step)).collect()
+}
+
+/// Like `run_simulation`, but checks after every step that a selected server is
+/// healthy and that `healthy_count` agrees with the server states. A broken
+/// invariant, or a panic inside the balancer, is raised naming the step that
+/// caused it.
+///
+/// ```
+/// use load_balancer_simulator::sim;
+/// use load_balancer_simulator::{RoundRobin, Server, ServerState};
+///
+/// let servers = (0..5).map(|id| Server::new(id, ServerState::Healthy)).collect();
+/// let mut lb = RoundRobin::new(servers);
+/// let steps = sim::random_steps(7, 1_000, 5);
+/// let results = sim::run_checked(&mut lb, &steps);
+/// assert!(results.len() <= steps.len());
+/// ```
+pub fn run_checked<T, L: LoadBalancer<T>>(lb: &mut L, steps: &[LbStep]) -> Vec<LoadBalancerResult> {
+    let mut results = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        let result = match panic::catch_unwind(AssertUnwindSafe(|| apply(lb, step))) {
+            Ok(result) => result,
+            Err(_) => panic!("balancer panicked at step {i}: {step:?}"),
+        };
+        if let Some(LoadBalancerResult::Selected { id }) = result {
+            assert!(
+                lb.server_state(id) == ServerState::Healthy,
+                "step {i}: {step:?} selected server {id}, which is not healthy"
+            );
+        }
+        let healthy = lb
+            .servers()
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .count();
+        assert!(
+            lb.healthy_count() == healthy,
+            "step {i}: {step:?} left healthy_count at {}, expected {healthy}",
+            lb.healthy_count()
+        );
+        results.extend(result);
+    }
+    results
+}
+
+/// Fraction of `selections` that went to each server `0..server_count`; all zero
+/// when there are no selections.
+pub fn distribution(selections: &[usize], server_count: usize) -> Vec<f64> {
+    assert!(server_count > 0);
+
+    if selections.is_empty() {
+        return vec![0.0; server_count];
+    }
+    selection_counts(selections, server_count)
+        .into_iter()
+        .map(|count| count as f64 / selections.len() as f64)
+        .collect()
+}
+
+/// Pearson's chi-square statistic of `selections` against the counts `weights`
+/// would give each server, `weights[i]` being the weight of server `i`. Zero is
+/// a perfect match; compare larger values against a chi-square table with
+/// `weights.len() - 1` degrees of freedom.
+pub fn chi_square(selections: &[usize], weights: &[u32]) -> f64 {
+    assert!(!selections.is_empty());
+    assert!(weights.iter().all(|&weight| weight > 0));
+
+    let total_weight: u64 = weights.iter().map(|&weight| u64::from(weight)).sum();
+    selection_counts(selections, weights.len())
+        .into_iter()
+        .zip(weights)
+        .map(|(observed, &weight)| {
+            let expected = selections.len() as f64 * f64::from(weight) / total_weight as f64;
+            (observed as f64 - expected).powi(2) / expected
+        })
+        .sum()
+}
+
+fn selection_counts(selections: &[usize], server_count: usize) -> Vec<usize> {
+    assert!(server_count > 0);
+
+    let mut counts = vec![0; server_count];
+    for &server in selections {
+        assert!(server < server_count, "selection {server} out of range");
+        counts[server] += 1;
+    }
+    counts
+}
+
+fn apply<T, L: LoadBalancer<T>>(lb: &mut L, step: &LbStep) -> Option<LoadBalancerResult> {
+    match *step {
+        LbStep::SelectServer => return Some(lb.select_server()),
+        LbStep::MarkHealthy(server_id) => lb.healthy_server(server_id),
+        LbStep::MarkUnhealthy(server_id) => lb.unhealthy_server(server_id),
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RoundRobin, Server};
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    #[test]
+    fn test_random_steps_are_deterministic() {
+        assert_eq!(random_steps(1, 100, 4), random_steps(1, 100, 4));
+        assert_ne!(random_steps(1, 100, 4), random_steps(2, 100, 4));
+        assert!(random_steps(3, 100, 4).iter().all(|step| match step {
+            LbStep::SelectServer => true,
+            LbStep::MarkHealthy(id) | LbStep::MarkUnhealthy(id) => *id < 4,
+        }));
+    }
+
+    #[test]
+    fn test_run_simulation_reports_each_selection() {
+        let mut lb = RoundRobin::new(servers(2));
+        let steps = [
+            LbStep::SelectServer,
+            LbStep::MarkUnhealthy(1),
+            LbStep::SelectServer,
+            LbStep::MarkUnhealthy(0),
+            LbStep::SelectServer,
+        ];
+        assert_eq!(
+            run_simulation(&mut lb, &steps),
+            vec![
+                LoadBalancerResult::Selected { id: 0 },
+                LoadBalancerResult::Selected { id: 0 },
+                LoadBalancerResult::NoHealthyServers,
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "balancer panicked at step 1")]
+    fn test_run_checked_names_failing_step() {
+        let mut lb = RoundRobin::new(servers(2));
+        let _ = run_checked(&mut lb, &[LbStep::SelectServer, LbStep::MarkHealthy(9)]);
+    }
+
+    #[test]
+    fn test_distribution_counts_fractions() {
+        assert_eq!(distribution(&[0, 1, 1, 3], 4), vec![0.25, 0.5, 0.0, 0.25]);
+        assert_eq!(distribution(&[], 2), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "selection 2 out of range")]
+    fn test_distribution_out_of_range_panics() {
+        let _ = distribution(&[0, 2], 2);
+    }
+
+    #[test]
+    fn test_chi_square_against_weights() {
+        let mut lb = RoundRobin::new(servers(3));
+        let selections: Vec<usize> = (0..300)
+            .map(|_| match lb.select_server() {
+                LoadBalancerResult::Selected { id } => id,
+                other => panic!("expected a selection, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(chi_square(&selections, &[1, 1, 1]), 0.0);
+
+        let skewed = [0, 0, 0, 1];
+        assert_eq!(chi_square(&skewed, &[3, 1]), 0.0);
+        assert_eq!(chi_square(&skewed, &[1, 1]), 1.0);
+    }
+}
@@ -0,0 +1,110 @@
+//! Thread-safe wrapper around any load balancer
+
+use crate::{LoadBalancer, LoadBalancerResult};
+use std::sync::{Mutex, MutexGuard};
+
+/// Shares one balancer across threads. `Send + Sync` whenever the inner balancer
+/// is `Send`.
+///
+/// Every method takes the lock for the length of one call on the inner balancer.
+/// A panic inside the inner balancer poisons the lock, and later calls panic too.
+#[derive(Debug)]
+pub struct SyncLb<L> {
+    inner: Mutex<L>,
+}
+
+impl<L> SyncLb<L> {
+    pub fn new(inner: L) -> Self {
+        SyncLb {
+            inner: Mutex::new(inner),
+        }
+    }
+
+    pub fn select_server<T>(&self) -> LoadBalancerResult
+    where
+        L: LoadBalancer<T>,
+    {
+        self.lock().select_server()
+    }
+
+    pub fn healthy_server<T>(&self, server_id: usize)
+    where
+        L: LoadBalancer<T>,
+    {
+        self.lock().healthy_server(server_id)
+    }
+
+    pub fn unhealthy_server<T>(&self, server_id: usize)
+    where
+        L: LoadBalancer<T>,
+    {
+        self.lock().unhealthy_server(server_id)
+    }
+
+    pub fn count<T>(&self) -> usize
+    where
+        L: LoadBalancer<T>,
+    {
+        self.lock().count()
+    }
+
+    pub fn into_inner(self) -> L {
+        self.inner.into_inner().unwrap()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, L> {
+        self.inner.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LeastConnections, RoundRobin, Server, ServerState};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn servers(count: usize) -> Vec<Server> {
+        (0..count)
+            .map(|id| Server::new(id, ServerState::Healthy))
+            .collect()
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_sync_lb_is_send_and_sync() {
+        assert_send_sync::<SyncLb<RoundRobin>>();
+        assert_send_sync::<SyncLb<LeastConnections>>();
+    }
+
+    #[test]
+    fn test_threads_selecting_from_shared_balancer() {
+        let lb = Arc::new(SyncLb::new(RoundRobin::new(servers(4))));
+        let handles: Vec<_> = (0..8)
+            .map(|thread_id| {
+                let lb = Arc::clone(&lb);
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        if i % 100 == 0 {
+                            lb.unhealthy_server(1 + thread_id % 3);
+                            lb.healthy_server(1 + thread_id % 3);
+                        }
+                        assert!(matches!(
+                            lb.select_server(),
+                            LoadBalancerResult::Selected { .. }
+                        ));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(lb.count(), 4);
+        let lb = Arc::into_inner(lb).unwrap().into_inner();
+        assert_eq!(lb.metrics().total_selections, 8 * 1000);
+        assert_eq!(lb.metrics().per_server.iter().sum::<u64>(), 8 * 1000);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::lb::Slots;
+use crate::{LoadBalancer, LoadBalancerResult, Server, ServerState};
+
+/// Round-robin over the lowest `Server.priority` tier that has a healthy server,
+/// such as the local zone, spilling to the next tier only while every server of
+/// the tiers before it is out of rotation.
+///
+/// One cursor is shared by all tiers, so traffic returning to a recovered tier
+/// picks up from wherever the rotation last stopped.
+pub struct TieredRoundRobin<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
+    current_index: usize,
+}
+
+impl<T> TieredRoundRobin<T> {
+    pub fn new(servers: Vec<Server<T>>) -> Self {
+        assert!(!servers.is_empty());
+
+        Self {
+            slots: Slots::new(&servers),
+            servers,
+            current_index: 0,
+        }
+    }
+
+    /// The tier selections currently go to, `None` when no server is healthy.
+    pub fn active_priority(&self) -> Option<u32> {
+        self.lowest_priority(&[])
+    }
+
+    fn lowest_priority(&self, exclude: &[usize]) -> Option<u32> {
+        self.servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy && !exclude.contains(&s.id))
+            .map(|s| s.priority)
+            .min()
+    }
+
+    /// Round-robin over the lowest tier with a healthy server not in `exclude`.
+    fn select(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        assert!(self.current_index < self.servers.len());
+
+        let Some(priority) = self.lowest_priority(exclude) else {
+            return LoadBalancerResult::NoHealthyServers;
+        };
+        for offset in 0..self.servers.len() {
+            let slot = (self.current_index + offset) % self.servers.len();
+            let server = &self.servers[slot];
+            let eligible = server.state == ServerState::Healthy && !exclude.contains(&server.id);
+            if eligible && server.priority == priority {
+                self.current_index = (slot + 1) % self.servers.len();
+                return LoadBalancerResult::Selected { id: server.id };
+            }
+        }
+        unreachable!("the active tier has a healthy server");
+    }
+}
+
+impl<T> LoadBalancer<T> for TieredRoundRobin<T> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        self.select(&[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        self.select(exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Healthy;
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Unhealthy;
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Draining;
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+
+        for (server, &state) in self.servers.iter_mut().zip(states) {
+            server.state = state;
+        }
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.slots.push(server.id, self.servers.len());
+        self.servers.push(server);
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+
+        let slot = self.slots.remove(server_id);
+        self.servers.remove(slot);
+        // Keep the cursor on the server it pointed at, or its successor.
+        if self.current_index > slot {
+            self.current_index -= 1;
+        }
+        if self.current_index == self.servers.len() {
+            self.current_index = 0;
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_tiers() -> Vec<Server> {
+        (0..4)
+            .map(|id| Server::new(id, ServerState::Healthy).with_priority(id as u32 / 2))
+            .collect()
+    }
+
+    fn select_ids(lb: &mut TieredRoundRobin, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| match lb.select_server() {
+                LoadBalancerResult::Selected { id } => id,
+                other => panic!("expected a selection, got {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_empty_servers_panics() {
+        let _: TieredRoundRobin = TieredRoundRobin::new(vec![]);
+    }
+
+    #[test]
+    fn test_second_tier_only_after_first_tier_is_down() {
+        let mut lb = TieredRoundRobin::new(two_tiers());
+        assert_eq!(lb.active_priority(), Some(0));
+        assert_eq!(select_ids(&mut lb, 4), vec![0, 1, 0, 1]);
+
+        lb.unhealthy_server(0);
+        assert_eq!(select_ids(&mut lb, 3), vec![1, 1, 1]);
+
+        lb.drain_server(1);
+        assert_eq!(lb.active_priority(), Some(1));
+        assert_eq!(select_ids(&mut lb, 4), vec![2, 3, 2, 3]);
+
+        lb.healthy_server(0);
+        assert_eq!(select_ids(&mut lb, 2), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_all_tiers_down_returns_no_healthy_servers() {
+        let mut lb = TieredRoundRobin::new(two_tiers());
+        lb.set_states(&[ServerState::Unhealthy; 4]);
+        assert_eq!(lb.active_priority(), None);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+
+        lb.add_server(Server::new(4, ServerState::Healthy).with_priority(7));
+        assert_eq!(select_ids(&mut lb, 2), vec![4, 4]);
+    }
+
+    #[test]
+    fn test_select_excluding_spills_past_excluded_tier() {
+        let mut lb = TieredRoundRobin::new(two_tiers());
+        let selected: Vec<_> = (0..4).map(|_| lb.select_excluding(&[0, 1])).collect();
+        assert_eq!(
+            selected,
+            [2, 3, 2, 3].map(|id| LoadBalancerResult::Selected { id })
+        );
+        assert_eq!(
+            lb.select_excluding(&[0]),
+            LoadBalancerResult::Selected { id: 1 }
+        );
+        assert_eq!(
+            lb.select_excluding(&[0, 1, 2, 3]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Injectable time, so time-dependent balancers stay deterministic under test.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a test
+/// can keep one and hand the other to a balancer.
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    start: Instant,
+    elapsed: Rc<Cell<Duration>>,
+}
+
+impl TestClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Rc::new(Cell::new(Duration::ZERO)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.set(self.elapsed.get() + duration);
+    }
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed.get()
+    }
+}
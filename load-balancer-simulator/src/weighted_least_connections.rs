@@ -0,0 +1,363 @@
+use crate::lb::Slots;
+use crate::{Clock, LoadBalancer, LoadBalancerResult, Server, ServerState};
+use std::time::{Duration, Instant};
+
+/// Least-connections where each server's load is its connection count divided by
+/// its weight, so a server of weight 3 takes three times the connections of one
+/// of weight 1 before it looks as busy. Ties go to the server listed first.
+///
+/// With `warm_up`, a server that recovers from unhealthy, or is added healthy,
+/// starts at a sliver of its weight and ramps linearly to the full weight, so it
+/// is not flooded the moment it rejoins.
+pub struct WeightedLeastConnections<T = ()> {
+    servers: Vec<Server<T>>,
+    slots: Slots,
+    connection_counts: Vec<usize>,
+    warm_up: Option<WarmUp>,
+    // When each server started warming up, None once it runs at full weight.
+    warming_since: Vec<Option<Instant>>,
+}
+
+struct WarmUp {
+    duration: Duration,
+    clock: Box<dyn Clock>,
+}
+
+// Effective weights are kept in thousandths of a weight unit.
+const RAMP_STEPS: u64 = 1000;
+
+impl<T> WeightedLeastConnections<T> {
+    pub fn new(servers: Vec<Server<T>>) -> Self {
+        assert!(!servers.is_empty());
+        assert!(servers.iter().all(|s| s.weight > 0));
+        let connection_counts = vec![0; servers.len()];
+        let warming_since = vec![None; servers.len()];
+
+        Self {
+            slots: Slots::new(&servers),
+            servers,
+            connection_counts,
+            warm_up: None,
+            warming_since,
+        }
+    }
+
+    /// Ramps a server's weight from near zero to full over `duration` after it
+    /// turns healthy, reading time from `clock`. Servers passed to `new` start at
+    /// full weight.
+    pub fn warm_up(mut self, duration: Duration, clock: impl Clock + 'static) -> Self {
+        assert!(!duration.is_zero());
+
+        self.warm_up = Some(WarmUp {
+            duration,
+            clock: Box::new(clock),
+        });
+        self
+    }
+
+    /// `server_id`'s weight in thousandths, scaled down while it warms up.
+    pub fn effective_weight(&self, server_id: usize) -> u64 {
+        let slot = self.slots.get(server_id);
+
+        self.slot_weight(slot)
+    }
+
+    fn slot_weight(&self, slot: usize) -> u64 {
+        let full = u64::from(self.servers[slot].weight) * RAMP_STEPS;
+        let (Some(warm_up), Some(since)) = (&self.warm_up, self.warming_since[slot]) else {
+            return full;
+        };
+
+        let elapsed = warm_up.clock.now().saturating_duration_since(since);
+        if elapsed >= warm_up.duration {
+            return full;
+        }
+        let steps = elapsed.as_nanos() * u128::from(RAMP_STEPS) / warm_up.duration.as_nanos();
+        let weight = u128::from(self.servers[slot].weight) * steps;
+        assert!(weight < u128::from(full));
+        // Never zero, so a warming server is still comparable by load.
+        (weight as u64).max(1)
+    }
+
+    fn start_warming(&mut self, slot: usize) {
+        if let Some(warm_up) = &self.warm_up {
+            self.warming_since[slot] = Some(warm_up.clock.now());
+        }
+    }
+
+    pub fn connections(&self, server_id: usize) -> usize {
+        let slot = self.slots.get(server_id);
+
+        self.connection_counts[slot]
+    }
+
+    fn select(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        assert!(!self.servers.is_empty());
+        assert!(self.connection_counts.len() == self.servers.len());
+        assert!(self.warming_since.len() == self.servers.len());
+
+        let weights: Vec<u64> = (0..self.servers.len())
+            .map(|slot| self.slot_weight(slot))
+            .collect();
+        let mut best_server: Option<usize> = None;
+        for (i, server) in self.servers.iter().enumerate() {
+            if server.state != ServerState::Healthy || exclude.contains(&server.id) {
+                continue;
+            }
+            // connections_i / weight_i < connections_best / weight_best, without division.
+            let less_loaded = best_server.is_none_or(|best| {
+                let load = self.connection_counts[i] as u128 * u128::from(weights[best]);
+                let best_load = self.connection_counts[best] as u128 * u128::from(weights[i]);
+                load < best_load
+            });
+            if less_loaded {
+                best_server = Some(i);
+            }
+        }
+
+        match best_server {
+            Some(slot) => {
+                self.connection_counts[slot] += 1;
+                LoadBalancerResult::Selected {
+                    id: self.servers[slot].id,
+                }
+            }
+            None => LoadBalancerResult::NoHealthyServers,
+        }
+    }
+}
+
+impl<T> LoadBalancer<T> for WeightedLeastConnections<T> {
+    fn select_server(&mut self) -> LoadBalancerResult {
+        self.select(&[])
+    }
+
+    fn select_excluding(&mut self, exclude: &[usize]) -> LoadBalancerResult {
+        self.select(exclude)
+    }
+
+    fn healthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        if self.servers[slot].state == ServerState::Unhealthy {
+            self.start_warming(slot);
+        }
+        self.servers[slot].state = ServerState::Healthy;
+    }
+
+    fn unhealthy_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.servers[slot].state = ServerState::Unhealthy;
+        self.connection_counts[slot] = 0;
+        self.warming_since[slot] = None;
+    }
+
+    fn drain_server(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+
+        self.servers[slot].state = ServerState::Draining;
+    }
+
+    fn release_connection(&mut self, server_id: usize) {
+        let slot = self.slots.get(server_id);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        self.connection_counts[slot] = self.connection_counts[slot].saturating_sub(1);
+    }
+
+    fn set_states(&mut self, states: &[ServerState]) {
+        assert!(states.len() == self.servers.len());
+
+        for (slot, &state) in states.iter().enumerate() {
+            let server_id = self.servers[slot].id;
+            match state {
+                ServerState::Healthy => self.healthy_server(server_id),
+                ServerState::Unhealthy => self.unhealthy_server(server_id),
+                ServerState::Draining => self.drain_server(server_id),
+            }
+        }
+    }
+
+    fn add_server(&mut self, server: Server<T>) {
+        self.slots.push(server.id, self.servers.len());
+        assert!(server.weight > 0);
+        assert!(self.connection_counts.len() == self.servers.len());
+
+        let healthy = server.state == ServerState::Healthy;
+        self.servers.push(server);
+        self.connection_counts.push(0);
+        self.warming_since.push(None);
+        if healthy {
+            self.start_warming(self.servers.len() - 1);
+        }
+    }
+
+    fn remove_server(&mut self, server_id: usize) {
+        assert!(self.servers.len() > 1);
+
+        let slot = self.slots.remove(server_id);
+        self.servers.remove(slot);
+        self.connection_counts.remove(slot);
+        self.warming_since.remove(slot);
+    }
+
+    fn count(&self) -> usize {
+        self.servers.len()
+    }
+
+    fn server(&self, server_id: usize) -> &Server<T> {
+        let slot = self.slots.get(server_id);
+
+        &self.servers[slot]
+    }
+
+    fn servers(&self) -> &[Server<T>] {
+        &self.servers
+    }
+
+    fn healthy_count(&self) -> usize {
+        self.servers
+            .iter()
+            .filter(|s| s.state == ServerState::Healthy)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestClock;
+    use std::collections::VecDeque;
+
+    fn servers(weights: &[u32]) -> Vec<Server> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(id, &weight)| Server::with_weight(id, ServerState::Healthy, weight))
+            .collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_zero_weight_panics() {
+        let mut servers = servers(&[1, 1]);
+        servers[1].weight = 0;
+        let _ = WeightedLeastConnections::new(servers);
+    }
+
+    #[test]
+    fn test_heavier_server_takes_proportional_share() {
+        let mut lb = WeightedLeastConnections::new(servers(&[1, 3]));
+        for _ in 0..400 {
+            let _ = lb.select_server();
+        }
+        assert_eq!(lb.connections(0), 100);
+        assert_eq!(lb.connections(1), 300);
+    }
+
+    #[test]
+    fn test_ties_break_toward_lower_id() {
+        let mut lb = WeightedLeastConnections::new(servers(&[2, 2, 2]));
+        let selected: Vec<_> = (0..3).map(|_| lb.select_server()).collect();
+        assert_eq!(
+            selected,
+            [0, 1, 2]
+                .map(|id| LoadBalancerResult::Selected { id })
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_release_connection_lowers_load() {
+        let mut lb = WeightedLeastConnections::new(servers(&[1, 2]));
+        for _ in 0..3 {
+            let _ = lb.select_server();
+        }
+        assert_eq!(lb.connections(0), 1);
+        assert_eq!(lb.connections(1), 2);
+
+        lb.release_connection(0);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 0 });
+
+        lb.unhealthy_server(0);
+        assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        lb.unhealthy_server(1);
+        assert_eq!(lb.select_server(), LoadBalancerResult::NoHealthyServers);
+    }
+
+    #[test]
+    fn test_warm_up_ramps_recovered_server_share() {
+        let clock = TestClock::new();
+        let mut lb = WeightedLeastConnections::new(servers(&[1, 1]))
+            .warm_up(Duration::from_secs(10), clock.clone());
+        assert_eq!(lb.effective_weight(0), 1000);
+
+        lb.unhealthy_server(0);
+        let mut in_flight: VecDeque<usize> = VecDeque::new();
+        for _ in 0..20 {
+            in_flight.push_back(1);
+            assert_eq!(lb.select_server(), LoadBalancerResult::Selected { id: 1 });
+        }
+
+        lb.healthy_server(0);
+        assert_eq!(lb.effective_weight(0), 1);
+        let mut shares = Vec::new();
+        for _ in 0..12 {
+            let mut selected_zero = 0;
+            for _ in 0..20 {
+                lb.release_connection(in_flight.pop_front().unwrap());
+                let LoadBalancerResult::Selected { id } = lb.select_server() else {
+                    panic!("expected a selection");
+                };
+                in_flight.push_back(id);
+                selected_zero += usize::from(id == 0);
+            }
+            shares.push(selected_zero);
+            clock.advance(Duration::from_secs(1));
+        }
+        assert_eq!(shares, vec![1, 2, 4, 5, 6, 7, 8, 8, 9, 10, 10, 10]);
+        assert_eq!(lb.effective_weight(0), 1000);
+    }
+
+    #[test]
+    fn test_warm_up_only_after_recovery() {
+        let clock = TestClock::new();
+        let mut lb = WeightedLeastConnections::new(servers(&[2, 2]))
+            .warm_up(Duration::from_secs(4), clock.clone());
+        lb.healthy_server(1);
+        assert_eq!(lb.effective_weight(1), 2000);
+
+        lb.unhealthy_server(1);
+        lb.healthy_server(1);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lb.effective_weight(1), 500);
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(lb.effective_weight(1), 1500);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lb.effective_weight(1), 2000);
+
+        lb.add_server(Server::with_weight(2, ServerState::Healthy, 1));
+        assert_eq!(lb.effective_weight(2), 1);
+        lb.add_server(Server::with_weight(3, ServerState::Unhealthy, 1));
+        assert_eq!(lb.effective_weight(3), 1000);
+    }
+
+    #[test]
+    fn test_select_excluding_counts_only_the_selection() {
+        let mut lb = WeightedLeastConnections::new(servers(&[1, 1]));
+        for _ in 0..3 {
+            assert_eq!(
+                lb.select_excluding(&[0]),
+                LoadBalancerResult::Selected { id: 1 }
+            );
+        }
+        assert_eq!(lb.connections(0), 0);
+        assert_eq!(lb.connections(1), 3);
+        assert_eq!(
+            lb.select_excluding(&[0, 1]),
+            LoadBalancerResult::NoHealthyServers
+        );
+    }
+}